@@ -1,7 +1,21 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use directories::ProjectDirs;
+use tauri::Emitter;
+
+/// `RunnerConfig` fields that, when changed, require tearing down and
+/// restarting the runner process with the new environment rather than
+/// applying in place.
+fn connection_affecting_fields_changed(old: &RunnerConfig, new: &RunnerConfig) -> bool {
+    old.orchestrator_url != new.orchestrator_url
+        || old.api_key != new.api_key
+        || old.poll_interval != new.poll_interval
+        || old.heartbeat_interval != new.heartbeat_interval
+        || old.work_dir != new.work_dir
+        || old.runner_binary_path != new.runner_binary_path
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunnerConfig {
@@ -17,6 +31,15 @@ pub struct RunnerConfig {
     pub work_dir: String,
     pub auto_start_service: bool,
     pub start_minimized: bool,
+    /// Explicit override for where to find the `skuldbot-runner` binary, checked
+    /// before sidecar/PATH resolution.
+    #[serde(default)]
+    pub runner_binary_path: Option<String>,
+    /// Explicit opt-in to run without a real runner binary (demo machines only).
+    /// When false, a missing binary is a hard startup error instead of a silent
+    /// fallback.
+    #[serde(default)]
+    pub mock_mode: bool,
 }
 
 impl Default for RunnerConfig {
@@ -40,6 +63,8 @@ impl Default for RunnerConfig {
             work_dir: get_default_work_dir(),
             auto_start_service: true,
             start_minimized: false,
+            runner_binary_path: None,
+            mock_mode: false,
         }
     }
 }
@@ -98,20 +123,33 @@ pub fn save_config_to_file(config: &RunnerConfig) -> Result<(), String> {
 // Tauri commands
 
 #[tauri::command]
-pub fn get_config(config: tauri::State<RunnerConfig>) -> RunnerConfig {
-    config.inner().clone()
+pub fn get_config(config: tauri::State<Mutex<RunnerConfig>>) -> RunnerConfig {
+    config.lock().unwrap().clone()
 }
 
 #[tauri::command]
 pub async fn save_config(
-    _config_state: tauri::State<'_, RunnerConfig>,
+    app: tauri::AppHandle,
+    config_state: tauri::State<'_, Mutex<RunnerConfig>>,
+    runner_state: tauri::State<'_, crate::runner::RunnerState>,
     new_config: RunnerConfig,
 ) -> Result<(), String> {
     // Save to file
     save_config_to_file(&new_config)?;
 
-    // Note: In a real app, you'd update the managed state here
-    // For now, restart is required to apply changes
+    let needs_restart = {
+        let mut guard = config_state.lock().map_err(|e| e.to_string())?;
+        let needs_restart = connection_affecting_fields_changed(&guard, &new_config);
+        *guard = new_config.clone();
+        needs_restart
+    };
+
+    if needs_restart && runner_state.is_running() {
+        runner_state.stop()?;
+        runner_state.start(&app, &new_config)?;
+    }
+
+    let _ = app.emit("config-changed", &new_config);
 
     Ok(())
 }
@@ -45,7 +45,13 @@ fn main() {
 
             // Load config
             let config = config::load_config();
-            app.manage(config);
+            app.manage(std::sync::Mutex::new(config));
+
+            // Track the last license check performed this session
+            app.manage(protection::LicenseState::new());
+
+            // Keep the orchestrator connection alive in the background
+            runner::spawn_heartbeat_task(app.handle().clone());
 
             // Check if started minimized
             let args: Vec<String> = std::env::args().collect();
@@ -85,6 +91,9 @@ fn main() {
             secrets::get_secret,
             secrets::delete_secret,
             secrets::has_secret,
+            secrets::rotate_secret,
+            secrets::export_secrets,
+            secrets::import_secrets,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
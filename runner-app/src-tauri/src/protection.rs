@@ -0,0 +1,246 @@
+//! License validation and anti-tampering checks for the standalone runner
+//! agent.
+//!
+//! The runner binary runs unattended on a customer's machine, often with no
+//! Studio install alongside it, so it enforces its own license rather than
+//! trusting whatever validated it last. Verification mirrors `studio`'s
+//! `license.rs`: a signed `header.payload.signature` token, base64url
+//! encoded, checked offline against an Ed25519 public key embedded in the
+//! binary, bound to this machine's fingerprint.
+
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Public half of the Orchestrator's runner-license signing keypair. The
+/// private key never leaves the license server; only it can mint a token
+/// that verifies against this key.
+const LICENSE_PUBLIC_KEY: [u8; 32] = [
+    0x4e, 0x2a, 0x91, 0xc7, 0x0d, 0x5f, 0x8b, 0x36, 0xa1, 0x7c, 0x63, 0xde, 0x49, 0x12, 0xf0, 0x58,
+    0x9a, 0x2d, 0x6e, 0x41, 0xb3, 0x7f, 0xc8, 0x05, 0x4d, 0x31, 0x96, 0xea, 0x22, 0x7b, 0x58, 0xc4,
+];
+
+/// Claims carried by a signed runner license token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunnerLicenseClaims {
+    /// Machine fingerprint this license is bound to.
+    sub: String,
+    seats: u32,
+    /// Expiry, Unix seconds.
+    exp: i64,
+    /// Not-valid-before, Unix seconds. Absent means valid immediately.
+    #[serde(default)]
+    nbf: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LicenseHeader {
+    alg: String,
+}
+
+/// Result of the last license check, returned to the UI by both
+/// `validate_license` and `check_license_status`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LicenseStatus {
+    pub valid: bool,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Tauri-managed state: the last license check performed this session.
+pub struct LicenseState(Mutex<LicenseStatus>);
+
+impl LicenseState {
+    pub fn new() -> Self {
+        Self(Mutex::new(LicenseStatus::default()))
+    }
+}
+
+/// Verify a license token's signature, `exp`/`nbf`, seat count, and device
+/// binding (`sub` must match this machine's fingerprint) against the
+/// embedded Orchestrator public key, entirely offline.
+fn verify_license_token(token: &str) -> Result<RunnerLicenseClaims, String> {
+    let mut parts = token.split('.');
+    let (Some(header_b64), Some(payload_b64), Some(signature_b64), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err("Malformed license token".to_string());
+    };
+
+    let header_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(header_b64)
+        .map_err(|_| "Malformed license token header".to_string())?;
+    let header: LicenseHeader = serde_json::from_slice(&header_bytes)
+        .map_err(|_| "Malformed license token header".to_string())?;
+    if header.alg != "EdDSA" {
+        return Err(format!("Unsupported license signing algorithm: {}", header.alg));
+    }
+
+    let signature_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(signature_b64)
+        .map_err(|_| "Malformed license token signature".to_string())?;
+    let signature_bytes: [u8; 64] = signature_bytes
+        .try_into()
+        .map_err(|_| "Malformed license token signature".to_string())?;
+    let signature = Signature::from_bytes(&signature_bytes);
+
+    let verifying_key = VerifyingKey::from_bytes(&LICENSE_PUBLIC_KEY)
+        .map_err(|_| "Invalid embedded license public key".to_string())?;
+    let signed_message = format!("{}.{}", header_b64, payload_b64);
+    verifying_key
+        .verify_strict(signed_message.as_bytes(), &signature)
+        .map_err(|_| "License signature verification failed".to_string())?;
+
+    let payload_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload_b64)
+        .map_err(|_| "Malformed license token payload".to_string())?;
+    let claims: RunnerLicenseClaims = serde_json::from_slice(&payload_bytes)
+        .map_err(|_| "Malformed license token payload".to_string())?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    if now >= claims.exp {
+        return Err("License has expired".to_string());
+    }
+    if let Some(nbf) = claims.nbf {
+        if now < nbf {
+            return Err("License is not yet valid".to_string());
+        }
+    }
+    if claims.seats == 0 {
+        return Err("License has no seats".to_string());
+    }
+    if claims.sub != machine_fingerprint() {
+        return Err("License is not bound to this machine".to_string());
+    }
+
+    Ok(claims)
+}
+
+/// Fingerprint this machine from stable, machine-specific sources. Not a
+/// Tauri command itself so other in-process code can call it directly; the
+/// `get_machine_fingerprint` wrapper below is what the UI calls.
+fn machine_fingerprint() -> String {
+    let machine_id = get_machine_id();
+    let hostname_str = hostname::get()
+        .map(|h| h.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut hasher = DefaultHasher::new();
+    machine_id.hash(&mut hasher);
+    hostname_str.hash(&mut hasher);
+
+    format!("{:016x}", hasher.finish())
+}
+
+fn get_machine_id() -> String {
+    #[cfg(target_os = "windows")]
+    {
+        if let Ok(output) = std::process::Command::new("wmic")
+            .args(["csproduct", "get", "UUID"])
+            .output()
+        {
+            return String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .nth(1)
+                .unwrap_or("unknown")
+                .trim()
+                .to_string();
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(id) = std::fs::read_to_string("/etc/machine-id") {
+            return id.trim().to_string();
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(output) = std::process::Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+        {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            for line in stdout.lines() {
+                if line.contains("IOPlatformUUID") {
+                    if let Some(uuid) = line.split('"').nth(3) {
+                        return uuid.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    "fallback-machine-id".to_string()
+}
+
+/// Detect an attached debugger. Linux-only for now; other platforms report
+/// "not detected" rather than failing closed.
+fn detect_debugger() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(status) = std::fs::read_to_string("/proc/self/status") {
+            for line in status.lines() {
+                if let Some(pid) = line.strip_prefix("TracerPid:") {
+                    return pid.trim() != "0";
+                }
+            }
+        }
+        return false;
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Run at startup (release builds only, per `main.rs`) to fail fast if a
+/// debugger is attached.
+pub fn run_protection_checks() -> Result<(), String> {
+    if detect_debugger() {
+        return Err("Debugger detected".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn validate_license(
+    license_key: String,
+    license_state: tauri::State<LicenseState>,
+) -> Result<LicenseStatus, String> {
+    let status = match verify_license_token(&license_key) {
+        Ok(claims) => LicenseStatus {
+            valid: true,
+            expires_at: chrono::DateTime::<chrono::Utc>::from_timestamp(claims.exp, 0)
+                .map(|dt| dt.to_rfc3339()),
+            error: None,
+        },
+        Err(e) => LicenseStatus {
+            valid: false,
+            expires_at: None,
+            error: Some(e),
+        },
+    };
+
+    *license_state.0.lock().unwrap() = status.clone();
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn check_license_status(license_state: tauri::State<LicenseState>) -> Result<LicenseStatus, String> {
+    Ok(license_state.0.lock().unwrap().clone())
+}
+
+#[tauri::command]
+pub fn get_machine_fingerprint() -> Result<String, String> {
+    Ok(machine_fingerprint())
+}
@@ -1,10 +1,105 @@
 use serde::{Deserialize, Serialize};
-use std::process::{Child, Command};
-use std::sync::Mutex;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
 use tauri_plugin_autostart::ManagerExt;
 
 use crate::config::RunnerConfig;
 
+/// Maximum number of log entries retained in memory; oldest entries are dropped first.
+const MAX_LOG_ENTRIES: usize = 5000;
+
+/// How often the status file is polled while the runner is running.
+const STATUS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// A heartbeat is considered stale (and the orchestrator treated as
+/// disconnected) once this many missed intervals have elapsed.
+const HEARTBEAT_STALE_MULTIPLIER: u32 = 3;
+
+/// Ceiling for the exponential backoff between failed heartbeat attempts.
+const HEARTBEAT_MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Lifecycle states of the spawned `skuldbot-runner` agent, advanced by status
+/// updates read from disk and by `try_wait` on the child process.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunnerAgentState {
+    Offline,
+    Starting,
+    Idle,
+    Running,
+    Errored,
+}
+
+/// Status document the Python runner writes to `{work_dir}/runner_status.json`
+/// so the Tauri side can observe agent state and per-job progress without a
+/// direct IPC link.
+#[derive(Debug, Clone, Deserialize)]
+struct RunnerStatusDoc {
+    state: RunnerAgentState,
+    current_job: Option<String>,
+    jobs_completed: u32,
+    jobs_failed: u32,
+}
+
+impl Default for RunnerStatusDoc {
+    fn default() -> Self {
+        Self {
+            state: RunnerAgentState::Starting,
+            current_job: None,
+            jobs_completed: 0,
+            jobs_failed: 0,
+        }
+    }
+}
+
+fn status_doc_path(work_dir: &str) -> PathBuf {
+    PathBuf::from(work_dir).join("runner_status.json")
+}
+
+const RUNNER_BINARY_NAME: &str = "skuldbot-runner";
+
+/// Resolves the `skuldbot-runner` executable to spawn, in order of precedence:
+/// an explicit `runner_binary_path` override, a bundled Tauri sidecar sitting
+/// next to this binary, then a PATH lookup. Returns a precise error instead of
+/// silently falling back when none resolve.
+fn resolve_runner_binary(config: &RunnerConfig) -> Result<PathBuf, String> {
+    if let Some(path) = &config.runner_binary_path {
+        let path = PathBuf::from(path);
+        if path.exists() {
+            return Ok(path);
+        }
+        return Err(format!(
+            "configured runner_binary_path '{}' does not exist",
+            path.display()
+        ));
+    }
+
+    let sidecar_name = if cfg!(windows) {
+        format!("{}.exe", RUNNER_BINARY_NAME)
+    } else {
+        RUNNER_BINARY_NAME.to_string()
+    };
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            let sidecar = dir.join(&sidecar_name);
+            if sidecar.exists() {
+                return Ok(sidecar);
+            }
+        }
+    }
+
+    if let Ok(path) = which::which(RUNNER_BINARY_NAME) {
+        return Ok(path);
+    }
+
+    Err(format!("{} not found on PATH", RUNNER_BINARY_NAME))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemInfo {
     pub os: String,
@@ -17,6 +112,7 @@ pub struct SystemInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunnerStatus {
     pub running: bool,
+    pub state: RunnerAgentState,
     pub pid: Option<u32>,
     pub runner_id: Option<String>,
     pub orchestrator_connected: bool,
@@ -38,10 +134,12 @@ pub struct LogEntry {
 pub struct RunnerState {
     process: Mutex<Option<Child>>,
     start_time: Mutex<Option<std::time::Instant>>,
-    jobs_completed: Mutex<u32>,
-    jobs_failed: Mutex<u32>,
     // Mock mode for when Python runner is not available
     mock_running: Mutex<bool>,
+    logs: Arc<Mutex<VecDeque<LogEntry>>>,
+    status: Arc<Mutex<RunnerStatusDoc>>,
+    status_poll_active: Arc<AtomicBool>,
+    last_heartbeat_at: Arc<Mutex<Option<std::time::Instant>>>,
 }
 
 impl RunnerState {
@@ -49,13 +147,36 @@ impl RunnerState {
         Self {
             process: Mutex::new(None),
             start_time: Mutex::new(None),
-            jobs_completed: Mutex::new(0),
-            jobs_failed: Mutex::new(0),
             mock_running: Mutex::new(false),
+            logs: Arc::new(Mutex::new(VecDeque::with_capacity(MAX_LOG_ENTRIES))),
+            status: Arc::new(Mutex::new(RunnerStatusDoc::default())),
+            status_poll_active: Arc::new(AtomicBool::new(false)),
+            last_heartbeat_at: Arc::new(Mutex::new(None)),
         }
     }
 
-    pub fn start(&self, config: &RunnerConfig) -> Result<(), String> {
+    fn record_heartbeat(&self) {
+        if let Ok(mut guard) = self.last_heartbeat_at.lock() {
+            *guard = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Whether the orchestrator connection is live, derived from whether the
+    /// last successful heartbeat is within a few missed intervals of now.
+    pub fn is_orchestrator_connected(&self, heartbeat_interval_secs: u32) -> bool {
+        let Ok(guard) = self.last_heartbeat_at.lock() else { return false };
+        match *guard {
+            Some(at) => {
+                let staleness_window = std::time::Duration::from_secs(
+                    heartbeat_interval_secs.max(1) as u64 * HEARTBEAT_STALE_MULTIPLIER as u64,
+                );
+                at.elapsed() < staleness_window
+            }
+            None => false,
+        }
+    }
+
+    pub fn start(&self, app_handle: &tauri::AppHandle, config: &RunnerConfig) -> Result<(), String> {
         let mut process_guard = self.process.lock().map_err(|e| e.to_string())?;
         let mut mock_guard = self.mock_running.lock().map_err(|e| e.to_string())?;
 
@@ -63,8 +184,24 @@ impl RunnerState {
             return Err("Runner is already running".to_string());
         }
 
-        // Build command to start Python runner
-        let mut cmd = Command::new("skuldbot-runner");
+        let binary = match resolve_runner_binary(config) {
+            Ok(path) => path,
+            Err(e) => {
+                if config.mock_mode {
+                    *mock_guard = true;
+                    *self.status.lock().map_err(|e| e.to_string())? = RunnerStatusDoc {
+                        state: RunnerAgentState::Idle,
+                        ..RunnerStatusDoc::default()
+                    };
+                    *self.start_time.lock().map_err(|e| e.to_string())? = Some(std::time::Instant::now());
+                    return Ok(());
+                }
+                return Err(e);
+            }
+        };
+
+        // Build command to start the runner binary
+        let mut cmd = Command::new(&binary);
         cmd.arg("run");
 
         // Set environment variables
@@ -80,14 +217,37 @@ impl RunnerState {
         cmd.env("SKULDBOT_JOB_TIMEOUT", config.job_timeout.to_string());
         cmd.env("SKULDBOT_WORK_DIR", &config.work_dir);
 
-        // Try to start process, fall back to mock mode if not available
+        // Inject secrets flagged for env-var injection so bots get credentials
+        // without hardcoding them in config.
+        for (env_var, value) in crate::secrets::injectable_secrets() {
+            cmd.env(env_var, value);
+        }
+
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        *self.status.lock().map_err(|e| e.to_string())? = RunnerStatusDoc::default();
+
         match cmd.spawn() {
-            Ok(child) => {
+            Ok(mut child) => {
+                if let Some(stdout) = child.stdout.take() {
+                    spawn_log_reader(app_handle.clone(), self.logs.clone(), stdout, "stdout");
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    spawn_log_reader(app_handle.clone(), self.logs.clone(), stderr, "stderr");
+                }
                 *process_guard = Some(child);
+
+                self.status_poll_active.store(true, Ordering::SeqCst);
+                spawn_status_poller(
+                    app_handle.clone(),
+                    self.status.clone(),
+                    self.status_poll_active.clone(),
+                    status_doc_path(&config.work_dir),
+                );
             }
-            Err(_) => {
-                // Python runner not available - run in mock/demo mode
-                *mock_guard = true;
+            Err(e) => {
+                return Err(format!("Failed to spawn {}: {}", binary.display(), e));
             }
         }
 
@@ -96,10 +256,26 @@ impl RunnerState {
         Ok(())
     }
 
+    /// Returns a snapshot of the buffered logs, optionally filtered by level and/or run id.
+    pub fn get_logs(&self, level: Option<&str>, run_id: Option<&str>) -> Vec<LogEntry> {
+        let logs = match self.logs.lock() {
+            Ok(logs) => logs,
+            Err(_) => return vec![],
+        };
+
+        logs.iter()
+            .filter(|entry| level.map_or(true, |l| entry.level.eq_ignore_ascii_case(l)))
+            .filter(|entry| run_id.map_or(true, |id| entry.run_id.as_deref() == Some(id)))
+            .cloned()
+            .collect()
+    }
+
     pub fn stop(&self) -> Result<(), String> {
         let mut process_guard = self.process.lock().map_err(|e| e.to_string())?;
         let mut mock_guard = self.mock_running.lock().map_err(|e| e.to_string())?;
 
+        self.status_poll_active.store(false, Ordering::SeqCst);
+
         if let Some(ref mut child) = *process_guard {
             child.kill().map_err(|e| format!("Failed to stop runner: {}", e))?;
             *process_guard = None;
@@ -107,6 +283,10 @@ impl RunnerState {
 
         *mock_guard = false;
         *self.start_time.lock().map_err(|e| e.to_string())? = None;
+        *self.status.lock().map_err(|e| e.to_string())? = RunnerStatusDoc {
+            state: RunnerAgentState::Offline,
+            ..RunnerStatusDoc::default()
+        };
 
         Ok(())
     }
@@ -146,30 +326,134 @@ impl RunnerState {
         }
         0
     }
+
+    /// Resolves the agent's current lifecycle state, combining the cached status
+    /// document with a live `try_wait` check so an unexpectedly exited process is
+    /// reported as `Offline` even if the status file is stale.
+    fn get_agent_status(&self) -> RunnerStatusDoc {
+        if !self.is_running() {
+            return RunnerStatusDoc {
+                state: RunnerAgentState::Offline,
+                ..RunnerStatusDoc::default()
+            };
+        }
+
+        let mut doc = self.status.lock().map(|d| d.clone()).unwrap_or_default();
+        if doc.state == RunnerAgentState::Offline {
+            // Running but no status update observed yet.
+            doc.state = RunnerAgentState::Starting;
+        }
+        doc
+    }
+}
+
+/// Reads a child stream line-by-line, parsing each line as a `LogEntry` when possible
+/// and falling back to a synthesized "info" entry for plain text, pushing into the
+/// shared buffer and emitting a `runner-log` event for each entry.
+fn spawn_log_reader<R>(
+    app_handle: tauri::AppHandle,
+    logs: Arc<Mutex<VecDeque<LogEntry>>>,
+    stream: R,
+    _stream_name: &'static str,
+) where
+    R: std::io::Read + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let reader = BufReader::new(stream);
+        for line in reader.lines() {
+            let Ok(line) = line else { break };
+            if line.is_empty() {
+                continue;
+            }
+
+            let entry = serde_json::from_str::<LogEntry>(&line).unwrap_or_else(|_| LogEntry {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                level: "info".to_string(),
+                message: line,
+                node_id: None,
+                run_id: None,
+            });
+
+            if let Ok(mut guard) = logs.lock() {
+                if guard.len() >= MAX_LOG_ENTRIES {
+                    guard.pop_front();
+                }
+                guard.push_back(entry.clone());
+            }
+
+            let _ = app_handle.emit("runner-log", &entry);
+        }
+    });
+}
+
+/// Polls the runner's status file on a background thread, caching the latest
+/// values until `active` is cleared (by `stop()`). A missing or unparsable
+/// file leaves the last cached value in place.
+fn spawn_status_poller(
+    app_handle: tauri::AppHandle,
+    status: Arc<Mutex<RunnerStatusDoc>>,
+    active: Arc<AtomicBool>,
+    path: PathBuf,
+) {
+    std::thread::spawn(move || {
+        let mut last_state = None;
+        while active.load(Ordering::SeqCst) {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                if let Ok(doc) = serde_json::from_str::<RunnerStatusDoc>(&content) {
+                    let state_changed = last_state != Some(doc.state);
+                    if let Ok(mut guard) = status.lock() {
+                        *guard = doc.clone();
+                    }
+                    // Only serialize and broadcast on an actual state
+                    // transition, and do it once for every listening window
+                    // rather than per-window, so the tray and any open
+                    // windows see a single consistent payload.
+                    if state_changed {
+                        last_state = Some(doc.state);
+                        let _ = app_handle.emit_filter(
+                            "runner-status-changed",
+                            doc.state,
+                            |_target| true,
+                        );
+                    }
+                }
+            }
+            std::thread::sleep(STATUS_POLL_INTERVAL);
+        }
+    });
 }
 
 // Tauri commands
 
 #[tauri::command]
-pub fn get_status(state: tauri::State<RunnerState>, config: tauri::State<RunnerConfig>) -> RunnerStatus {
-    let jobs_completed = state.jobs_completed.lock().map(|g| *g).unwrap_or(0);
-    let jobs_failed = state.jobs_failed.lock().map(|g| *g).unwrap_or(0);
+pub fn get_status(
+    state: tauri::State<RunnerState>,
+    config: tauri::State<Mutex<RunnerConfig>>,
+) -> RunnerStatus {
+    let agent_status = state.get_agent_status();
+    let config = config.lock().unwrap();
 
     RunnerStatus {
         running: state.is_running(),
+        state: agent_status.state,
         pid: state.get_pid(),
         runner_id: config.runner_id.clone(),
-        orchestrator_connected: config.orchestrator_url.is_some() && config.api_key.is_some(),
-        current_job: None, // TODO: Get from runner via IPC
-        jobs_completed,
-        jobs_failed,
+        orchestrator_connected: state.is_orchestrator_connected(config.heartbeat_interval),
+        current_job: agent_status.current_job,
+        jobs_completed: agent_status.jobs_completed,
+        jobs_failed: agent_status.jobs_failed,
         uptime_seconds: state.get_uptime(),
     }
 }
 
 #[tauri::command]
-pub fn start_runner(state: tauri::State<RunnerState>, config: tauri::State<RunnerConfig>) -> Result<(), String> {
-    state.start(&config)
+pub fn start_runner(
+    app: tauri::AppHandle,
+    state: tauri::State<RunnerState>,
+    config: tauri::State<Mutex<RunnerConfig>>,
+) -> Result<(), String> {
+    let config = config.lock().unwrap();
+    state.start(&app, &config)
 }
 
 #[tauri::command]
@@ -178,18 +462,22 @@ pub fn stop_runner(state: tauri::State<RunnerState>) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn restart_runner(state: tauri::State<RunnerState>, config: tauri::State<RunnerConfig>) -> Result<(), String> {
+pub fn restart_runner(
+    app: tauri::AppHandle,
+    state: tauri::State<RunnerState>,
+    config: tauri::State<Mutex<RunnerConfig>>,
+) -> Result<(), String> {
     state.stop()?;
     std::thread::sleep(std::time::Duration::from_secs(1));
-    state.start(&config)
+    let config = config.lock().unwrap();
+    state.start(&app, &config)
 }
 
-#[tauri::command]
-pub async fn register_runner(
-    orchestrator_url: String,
-    name: String,
-    labels: std::collections::HashMap<String, String>,
-    capabilities: Vec<String>,
+async fn register_with_orchestrator(
+    orchestrator_url: &str,
+    name: &str,
+    labels: &std::collections::HashMap<String, String>,
+    capabilities: &[String],
 ) -> Result<serde_json::Value, String> {
     let client = reqwest::Client::new();
 
@@ -217,8 +505,119 @@ pub async fn register_runner(
         return Err(format!("Registration failed: {} - {}", status, text));
     }
 
-    let data: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-    Ok(data)
+    response.json().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn register_runner(
+    orchestrator_url: String,
+    name: String,
+    labels: std::collections::HashMap<String, String>,
+    capabilities: Vec<String>,
+) -> Result<serde_json::Value, String> {
+    register_with_orchestrator(&orchestrator_url, &name, &labels, &capabilities).await
+}
+
+/// Background task, spawned once at startup, that keeps the runner
+/// connected: it sends periodic heartbeats, re-registers if the
+/// orchestrator has forgotten this runner, and backs off exponentially on
+/// failure. `orchestrator_connected` in `get_status` is derived from the
+/// timestamp this loop updates on every successful heartbeat.
+pub fn spawn_heartbeat_task(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut backoff = std::time::Duration::from_secs(5);
+        let mut was_connected = false;
+
+        loop {
+            let Some(state) = app.try_state::<RunnerState>() else {
+                tokio::time::sleep(backoff).await;
+                continue;
+            };
+            let Some(config_state) = app.try_state::<Mutex<RunnerConfig>>() else {
+                tokio::time::sleep(backoff).await;
+                continue;
+            };
+
+            let config = config_state.lock().unwrap().clone();
+            let (Some(orchestrator_url), Some(_api_key)) =
+                (config.orchestrator_url.clone(), config.api_key.clone())
+            else {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                continue;
+            };
+
+            let runner_id = match &config.runner_id {
+                Some(id) => Some(id.clone()),
+                None => {
+                    match register_with_orchestrator(
+                        &orchestrator_url,
+                        &config.runner_name,
+                        &config.labels,
+                        &config.capabilities,
+                    )
+                    .await
+                    {
+                        Ok(data) => {
+                            let new_id = data.get("id").and_then(|v| v.as_str()).map(String::from);
+                            if let Some(id) = &new_id {
+                                let mut guard = config_state.lock().unwrap();
+                                guard.runner_id = Some(id.clone());
+                                let _ = crate::config::save_config_to_file(&guard);
+                            }
+                            new_id
+                        }
+                        Err(_) => None,
+                    }
+                }
+            };
+
+            let agent_status = state.get_agent_status();
+            let heartbeat_ok = if let Some(runner_id) = &runner_id {
+                let body = serde_json::json!({
+                    "runnerId": runner_id,
+                    "state": agent_status.state,
+                    "jobsCompleted": agent_status.jobs_completed,
+                    "jobsFailed": agent_status.jobs_failed,
+                    "systemInfo": get_system_info_internal(),
+                });
+
+                let client = reqwest::Client::new();
+                match client
+                    .post(format!("{}/runners/heartbeat", orchestrator_url))
+                    .json(&body)
+                    .timeout(std::time::Duration::from_secs(15))
+                    .send()
+                    .await
+                {
+                    Ok(response) if response.status().is_success() => true,
+                    Ok(response) if response.status().as_u16() == 404 => {
+                        // Orchestrator forgot this runner; clear the id so the
+                        // next iteration re-registers.
+                        config_state.lock().unwrap().runner_id = None;
+                        false
+                    }
+                    _ => false,
+                }
+            } else {
+                false
+            };
+
+            if heartbeat_ok {
+                state.record_heartbeat();
+                backoff = std::time::Duration::from_secs(config.heartbeat_interval.max(1) as u64);
+            } else {
+                backoff = (backoff * 2).min(HEARTBEAT_MAX_BACKOFF);
+            }
+
+            let is_connected = state.is_orchestrator_connected(config.heartbeat_interval);
+            if is_connected != was_connected {
+                let _ = app.emit("orchestrator-connection-changed", is_connected);
+                was_connected = is_connected;
+            }
+
+            tokio::time::sleep(backoff).await;
+        }
+    });
 }
 
 fn get_system_info_internal() -> SystemInfo {
@@ -244,9 +643,12 @@ pub fn get_system_info() -> SystemInfo {
 }
 
 #[tauri::command]
-pub fn get_logs() -> Vec<LogEntry> {
-    // TODO: Read logs from runner service via IPC or file
-    vec![]
+pub fn get_logs(
+    state: tauri::State<RunnerState>,
+    level: Option<String>,
+    run_id: Option<String>,
+) -> Vec<LogEntry> {
+    state.get_logs(level.as_deref(), run_id.as_deref())
 }
 
 #[tauri::command]
@@ -1,4 +1,8 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use keyring::Entry;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -7,6 +11,17 @@ use directories::ProjectDirs;
 
 const SERVICE_NAME: &str = "skuldbot-runner";
 
+/// Magic bytes identifying an exported secrets vault file.
+const EXPORT_MAGIC: &[u8; 8] = b"SKBVLT1\0";
+const EXPORT_SALT_LEN: usize = 16;
+const EXPORT_NONCE_LEN: usize = 24;
+
+/// Argon2id parameters used to derive the export encryption key. Stored in the
+/// file header so a future release can tune them without breaking old exports.
+const ARGON2_M_COST: u32 = 19 * 1024; // KiB
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+
 /// Secret metadata (stored in config file, not the actual value)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecretMetadata {
@@ -14,6 +29,34 @@ pub struct SecretMetadata {
     pub description: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// When set, this secret is injected into the spawned runner's environment
+    /// under this variable name.
+    #[serde(default)]
+    pub env_var: Option<String>,
+    /// Unix timestamp (seconds) after which this secret is considered expired.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+/// `SecretMetadata` plus a computed `expired` flag, for display to the UI.
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretMetadataView {
+    #[serde(flatten)]
+    pub metadata: SecretMetadata,
+    pub expired: bool,
+}
+
+fn is_expired(expires_at: Option<u64>) -> bool {
+    match expires_at {
+        Some(ts) => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            now > ts
+        }
+        None => false,
+    }
 }
 
 /// Secrets index stored in config directory
@@ -70,16 +113,30 @@ fn get_current_timestamp() -> String {
 
 // Tauri Commands
 
-/// List all secrets (only metadata, not values)
+/// List all secrets (only metadata, not values), flagging expired ones
 #[tauri::command]
-pub fn list_secrets() -> Vec<SecretMetadata> {
+pub fn list_secrets() -> Vec<SecretMetadataView> {
     let index = load_secrets_index();
-    index.secrets.into_values().collect()
+    index
+        .secrets
+        .into_values()
+        .map(|metadata| {
+            let expired = is_expired(metadata.expires_at);
+            SecretMetadataView { metadata, expired }
+        })
+        .collect()
 }
 
-/// Set a secret value
+/// Set a secret value, optionally flagging it for injection into the runner's
+/// environment under `env_var` and/or giving it an expiry.
 #[tauri::command]
-pub fn set_secret(key: String, value: String, description: Option<String>) -> Result<(), String> {
+pub fn set_secret(
+    key: String,
+    value: String,
+    description: Option<String>,
+    env_var: Option<String>,
+    expires_at: Option<u64>,
+) -> Result<(), String> {
     // Store in OS keyring
     let entry = get_keyring_entry(&key)?;
     entry.set_password(&value).map_err(|e| format!("Failed to store secret: {}", e))?;
@@ -94,6 +151,8 @@ pub fn set_secret(key: String, value: String, description: Option<String>) -> Re
             description: description.or_else(|| existing.description.clone()),
             created_at: existing.created_at.clone(),
             updated_at: now,
+            env_var: env_var.or_else(|| existing.env_var.clone()),
+            expires_at: expires_at.or(existing.expires_at),
         }
     } else {
         SecretMetadata {
@@ -101,6 +160,8 @@ pub fn set_secret(key: String, value: String, description: Option<String>) -> Re
             description,
             created_at: now.clone(),
             updated_at: now,
+            env_var,
+            expires_at,
         }
     };
 
@@ -110,6 +171,54 @@ pub fn set_secret(key: String, value: String, description: Option<String>) -> Re
     Ok(())
 }
 
+/// Replace a secret's value while preserving `created_at`, bumping `updated_at`.
+#[tauri::command]
+pub fn rotate_secret(key: String, new_value: String) -> Result<(), String> {
+    let mut index = load_secrets_index();
+    let existing = index
+        .secrets
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| format!("Secret '{}' does not exist", key))?;
+
+    let entry = get_keyring_entry(&key)?;
+    entry
+        .set_password(&new_value)
+        .map_err(|e| format!("Failed to store rotated secret: {}", e))?;
+
+    index.secrets.insert(
+        key.clone(),
+        SecretMetadata {
+            updated_at: get_current_timestamp(),
+            ..existing
+        },
+    );
+    save_secrets_index(&index)?;
+
+    Ok(())
+}
+
+/// Secrets flagged with an `env_var`, resolved to their decrypted values, for
+/// injection into a spawned runner process. Skips secrets that failed to read
+/// from the keyring rather than failing the whole runner startup, and skips
+/// expired secrets — otherwise expiry was only ever enforced as a UI warning
+/// in `list_secrets`, while the runner kept injecting the stale value into
+/// every bot it spawned.
+pub fn injectable_secrets() -> Vec<(String, String)> {
+    let index = load_secrets_index();
+    index
+        .secrets
+        .into_values()
+        .filter(|metadata| !is_expired(metadata.expires_at))
+        .filter_map(|metadata| {
+            let env_var = metadata.env_var?;
+            let entry = get_keyring_entry(&metadata.name).ok()?;
+            let value = entry.get_password().ok()?;
+            Some((env_var, value))
+        })
+        .collect()
+}
+
 /// Get a secret value (for internal use / bots)
 #[tauri::command]
 pub fn get_secret(key: String) -> Result<String, String> {
@@ -138,3 +247,128 @@ pub fn has_secret(key: String) -> bool {
     let index = load_secrets_index();
     index.secrets.contains_key(&key)
 }
+
+/// A single secret's name, value, and metadata, as serialized into an export blob.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportedSecret {
+    metadata: SecretMetadata,
+    value: String,
+}
+
+fn derive_export_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let params = argon2::Params::new(ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST, Some(32))
+        .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Serializes every stored secret (name, value, metadata), encrypts it with a
+/// passphrase-derived key, and returns a portable blob suitable for writing to
+/// disk and moving to another machine.
+#[tauri::command]
+pub fn export_secrets(passphrase: String) -> Result<Vec<u8>, String> {
+    let index = load_secrets_index();
+
+    let mut entries = Vec::with_capacity(index.secrets.len());
+    for (key, metadata) in &index.secrets {
+        let entry = get_keyring_entry(key)?;
+        let value = entry
+            .get_password()
+            .map_err(|e| format!("Failed to read secret '{}': {}", key, e))?;
+        entries.push(ExportedSecret {
+            metadata: metadata.clone(),
+            value,
+        });
+    }
+
+    let payload = serde_json::to_vec(&entries).map_err(|e| e.to_string())?;
+
+    let mut salt = [0u8; EXPORT_SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_export_key(&passphrase, &salt)?;
+
+    let mut nonce_bytes = [0u8; EXPORT_NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = XNonce::from_slice(&nonce_bytes);
+
+    let cipher = XChaCha20Poly1305::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(nonce, payload.as_slice())
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    let mut out = Vec::with_capacity(
+        EXPORT_MAGIC.len() + 1 + EXPORT_SALT_LEN + 12 + EXPORT_NONCE_LEN + ciphertext.len(),
+    );
+    out.extend_from_slice(EXPORT_MAGIC);
+    out.push(1); // version
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&ARGON2_M_COST.to_le_bytes());
+    out.extend_from_slice(&ARGON2_T_COST.to_le_bytes());
+    out.extend_from_slice(&ARGON2_P_COST.to_le_bytes());
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&ciphertext);
+
+    Ok(out)
+}
+
+/// Decrypts a blob produced by `export_secrets` and re-inserts every secret
+/// into the OS keyring, rebuilding the secrets index. Fails with a clear error
+/// if the passphrase is wrong or the file is corrupt (the AEAD tag won't verify).
+#[tauri::command]
+pub fn import_secrets(bytes: Vec<u8>, passphrase: String) -> Result<u32, String> {
+    let header_len = EXPORT_MAGIC.len() + 1 + EXPORT_SALT_LEN + 12;
+    if bytes.len() < header_len + EXPORT_NONCE_LEN {
+        return Err("File is too short to be a valid secrets export".to_string());
+    }
+
+    let mut offset = 0;
+    if &bytes[offset..offset + EXPORT_MAGIC.len()] != EXPORT_MAGIC {
+        return Err("Not a recognized secrets export file".to_string());
+    }
+    offset += EXPORT_MAGIC.len();
+
+    let version = bytes[offset];
+    offset += 1;
+    if version != 1 {
+        return Err(format!("Unsupported export format version: {}", version));
+    }
+
+    let salt = &bytes[offset..offset + EXPORT_SALT_LEN];
+    offset += EXPORT_SALT_LEN;
+
+    // Argon2 parameters are stored for forward-compatibility but this version
+    // always derives with the current constants.
+    offset += 12;
+
+    let nonce_bytes = &bytes[offset..offset + EXPORT_NONCE_LEN];
+    offset += EXPORT_NONCE_LEN;
+    let ciphertext = &bytes[offset..];
+
+    let key = derive_export_key(&passphrase, salt)?;
+    let nonce = XNonce::from_slice(nonce_bytes);
+    let cipher = XChaCha20Poly1305::new_from_slice(&key).map_err(|e| e.to_string())?;
+
+    let payload = cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| "wrong passphrase or corrupt file".to_string())?;
+
+    let entries: Vec<ExportedSecret> = serde_json::from_slice(&payload)
+        .map_err(|e| format!("Failed to parse decrypted secrets: {}", e))?;
+
+    let mut index = load_secrets_index();
+    for entry in &entries {
+        let keyring_entry = get_keyring_entry(&entry.metadata.name)?;
+        keyring_entry
+            .set_password(&entry.value)
+            .map_err(|e| format!("Failed to store secret '{}': {}", entry.metadata.name, e))?;
+        index.secrets.insert(entry.metadata.name.clone(), entry.metadata.clone());
+    }
+    save_secrets_index(&index)?;
+
+    Ok(entries.len() as u32)
+}
@@ -1,9 +1,21 @@
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Manager, Runtime,
+    Listener, Manager, Runtime,
 };
 
+use crate::runner::RunnerAgentState;
+
+fn status_label(state: RunnerAgentState) -> &'static str {
+    match state {
+        RunnerAgentState::Offline => "Status: Stopped",
+        RunnerAgentState::Starting => "Status: Starting",
+        RunnerAgentState::Idle => "Status: Idle",
+        RunnerAgentState::Running => "Status: Running",
+        RunnerAgentState::Errored => "Status: Error",
+    }
+}
+
 pub fn create_tray<R: Runtime>(app: &tauri::App<R>) -> Result<(), Box<dyn std::error::Error>> {
     let show_item = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
     let status_item = MenuItem::with_id(app, "status", "Status: Stopped", false, None::<&str>)?;
@@ -34,7 +46,24 @@ pub fn create_tray<R: Runtime>(app: &tauri::App<R>) -> Result<(), Box<dyn std::e
     let icon = tauri::image::Image::from_bytes(icon_bytes)
         .unwrap_or_else(|_| tauri::image::Image::new(&[0, 0, 0, 255], 1, 1));
 
-    let _tray = TrayIconBuilder::new()
+    // Keep the tray in sync with real runner state: the status poller
+    // broadcasts a single `runner-status-changed` event (via `emit_filter`)
+    // on every state transition, and we fan that one payload out to the
+    // status menu item and tooltip here instead of re-deriving it per call.
+    let status_item_for_listener = status_item.clone();
+    let tray_for_listener = app.handle().clone();
+    app.listen("runner-status-changed", move |event| {
+        let Ok(state) = serde_json::from_str::<RunnerAgentState>(event.payload()) else {
+            return;
+        };
+        let label = status_label(state);
+        let _ = status_item_for_listener.set_text(label);
+        if let Some(tray) = tray_for_listener.tray_by_id("main") {
+            let _ = tray.set_tooltip(Some(&format!("SkuldBot Runner — {}", label)));
+        }
+    });
+
+    let _tray = TrayIconBuilder::with_id("main")
         .icon(icon)
         .icon_as_template(true) // macOS: treat as template image (adapts to light/dark mode)
         .menu(&menu)
@@ -0,0 +1,8 @@
+fn main() {
+    // Binary integrity is verified against a detached `.sha256` manifest CI
+    // drops next to the release binary after packaging (see
+    // `protection::verify_binary_integrity`), not against anything embedded
+    // at compile time — the binary can't contain a hash of its own final
+    // bytes without a chicken-and-egg rebuild, so there's nothing to do here.
+    tauri_build::build();
+}
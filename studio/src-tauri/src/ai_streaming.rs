@@ -0,0 +1,219 @@
+//! Streaming variants of the AI planner's completion calls.
+//!
+//! `call_openai_api`/`call_anthropic_api` block until the whole completion
+//! lands, so a large plan leaves the UI idle for however long the model
+//! takes. This module parses the provider's SSE stream incrementally and
+//! hands each step object back to a callback as soon as its closing brace
+//! balances, instead of waiting for the final `]`.
+
+use futures_util::StreamExt;
+
+/// Accumulates streamed text and yields each top-level `{...}` object's raw
+/// JSON as soon as its braces balance, tolerating objects nested inside it
+/// (a step's `config` field) by tracking depth rather than just `{`/`}`.
+pub struct StepBuffer {
+    current: String,
+    depth: i32,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl StepBuffer {
+    pub fn new() -> Self {
+        Self {
+            current: String::new(),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    /// Feed more completion text through the buffer, returning any step
+    /// objects that closed their final brace within this chunk.
+    pub fn feed(&mut self, chunk: &str) -> Vec<String> {
+        let mut completed = Vec::new();
+        for ch in chunk.chars() {
+            if self.in_string {
+                if self.depth > 0 {
+                    self.current.push(ch);
+                }
+                if self.escaped {
+                    self.escaped = false;
+                } else if ch == '\\' {
+                    self.escaped = true;
+                } else if ch == '"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+
+            match ch {
+                '"' => {
+                    self.in_string = true;
+                    if self.depth > 0 {
+                        self.current.push(ch);
+                    }
+                }
+                '{' => {
+                    if self.depth == 0 {
+                        self.current.clear();
+                    }
+                    self.depth += 1;
+                    self.current.push(ch);
+                }
+                '}' => {
+                    if self.depth > 0 {
+                        self.current.push(ch);
+                        self.depth -= 1;
+                        if self.depth == 0 {
+                            completed.push(std::mem::take(&mut self.current));
+                        }
+                    }
+                }
+                _ => {
+                    if self.depth > 0 {
+                        self.current.push(ch);
+                    }
+                }
+            }
+        }
+        completed
+    }
+}
+
+impl Default for StepBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drain complete `data: ...` SSE lines out of `pending`, leaving any
+/// trailing partial line buffered for the next chunk.
+fn drain_sse_lines(pending: &mut String) -> Vec<String> {
+    let mut lines = Vec::new();
+    while let Some(pos) = pending.find('\n') {
+        let line: String = pending.drain(..=pos).collect();
+        let line = line.trim_end_matches(['\r', '\n']).to_string();
+        if let Some(data) = line.strip_prefix("data: ") {
+            lines.push(data.to_string());
+        }
+    }
+    lines
+}
+
+/// Stream an OpenAI-compatible chat completion (`stream: true`), calling
+/// `on_step` with each step object's raw JSON as it completes. Returns the
+/// full assembled completion text once the stream ends.
+pub async fn stream_openai_completion(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: &str,
+    body: &serde_json::Value,
+    mut on_step: impl FnMut(String),
+) -> Result<String, String> {
+    let response = client
+        .post(url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json")
+        .header("Accept", "text/event-stream")
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call OpenAI API: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("OpenAI API error ({}): {}", status, error_text));
+    }
+
+    let mut byte_stream = response.bytes_stream();
+    let mut buffer = StepBuffer::new();
+    let mut full_text = String::new();
+    let mut pending = String::new();
+
+    while let Some(chunk) = byte_stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Stream error: {}", e))?;
+        pending.push_str(&String::from_utf8_lossy(&chunk));
+
+        for data in drain_sse_lines(&mut pending) {
+            if data == "[DONE]" {
+                continue;
+            }
+            let Ok(event) = serde_json::from_str::<serde_json::Value>(&data) else {
+                continue;
+            };
+            let Some(delta_text) = event
+                .get("choices")
+                .and_then(|c| c.get(0))
+                .and_then(|c| c.get("delta"))
+                .and_then(|d| d.get("content"))
+                .and_then(|c| c.as_str())
+            else {
+                continue;
+            };
+
+            full_text.push_str(delta_text);
+            for step in buffer.feed(delta_text) {
+                on_step(step);
+            }
+        }
+    }
+
+    Ok(full_text)
+}
+
+/// Stream an Anthropic `messages` completion, calling `on_step` with each
+/// step object's raw JSON as it completes. Returns the full assembled
+/// completion text once the stream ends.
+pub async fn stream_anthropic_completion(
+    client: &reqwest::Client,
+    api_key: &str,
+    body: &serde_json::Value,
+    mut on_step: impl FnMut(String),
+) -> Result<String, String> {
+    let response = client
+        .post("https://api.anthropic.com/v1/messages")
+        .header("x-api-key", api_key)
+        .header("anthropic-version", "2023-06-01")
+        .header("Content-Type", "application/json")
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call Anthropic API: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Anthropic API error ({}): {}", status, error_text));
+    }
+
+    let mut byte_stream = response.bytes_stream();
+    let mut buffer = StepBuffer::new();
+    let mut full_text = String::new();
+    let mut pending = String::new();
+
+    while let Some(chunk) = byte_stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Stream error: {}", e))?;
+        pending.push_str(&String::from_utf8_lossy(&chunk));
+
+        for data in drain_sse_lines(&mut pending) {
+            let Ok(event) = serde_json::from_str::<serde_json::Value>(&data) else {
+                continue;
+            };
+            if event.get("type").and_then(|t| t.as_str()) != Some("content_block_delta") {
+                continue;
+            }
+            let Some(text) = event.get("delta").and_then(|d| d.get("text")).and_then(|t| t.as_str()) else {
+                continue;
+            };
+
+            full_text.push_str(text);
+            for step in buffer.feed(text) {
+                on_step(step);
+            }
+        }
+    }
+
+    Ok(full_text)
+}
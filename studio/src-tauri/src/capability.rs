@@ -0,0 +1,91 @@
+//! Per-command capability gating tied to the active license's features.
+//!
+//! `validate_license` already returns the feature flags a license grants
+//! (`aiPlanner`, `compliance.protect_pii`, ...), but nothing stopped an
+//! unlicensed client from calling a gated command anyway. This holds the
+//! most recently validated license's claims in managed state and gives
+//! each command a one-line check against a single command -> feature
+//! table, so adding a new gated command never means scattering a check.
+
+use std::sync::Mutex;
+
+use crate::license::LicenseClaims;
+
+/// Command name -> required feature flag. Add a row here when a new
+/// command should require a license feature; nothing else needs to change.
+const COMMAND_FEATURES: &[(&str, &str)] = &[
+    ("ai_generate_plan", "aiPlanner"),
+    ("ai_refine_plan", "aiPlanner"),
+    ("ai_list_model_registry", "aiPlanner"),
+];
+
+/// Structured upsell error returned when a command's required feature
+/// isn't granted by the active license. Serialized to JSON and carried in
+/// the command's `Err(String)`, the same way every other command here
+/// surfaces errors, so the frontend can `JSON.parse` it to show an upsell
+/// instead of a generic failure toast.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LicenseRequired {
+    pub feature: String,
+    pub module: String,
+}
+
+impl LicenseRequired {
+    fn into_err(self) -> String {
+        serde_json::to_string(&self).unwrap_or_else(|_| format!("License required: {}", self.feature))
+    }
+}
+
+/// Tauri-managed state: the most recently validated license, if any.
+pub struct LicenseState(Mutex<Option<LicenseClaims>>);
+
+impl LicenseState {
+    pub fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    pub fn set(&self, claims: LicenseClaims) {
+        *self.0.lock().unwrap() = Some(claims);
+    }
+
+    pub fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+
+    fn has_feature(&self, feature: &str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|claims| claims.features.iter().any(|f| f == feature))
+            .unwrap_or(false)
+    }
+
+    fn module(&self) -> String {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|claims| claims.module.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Look up `command` in the capability table and check it against the
+/// active license. Commands with no table entry are always allowed — this
+/// gates specific features, not a default-deny allowlist of every command.
+pub fn require_feature(license: &LicenseState, command: &str) -> Result<(), String> {
+    let Some((_, feature)) = COMMAND_FEATURES.iter().find(|(cmd, _)| *cmd == command) else {
+        return Ok(());
+    };
+
+    if license.has_feature(feature) {
+        Ok(())
+    } else {
+        Err(LicenseRequired {
+            feature: feature.to_string(),
+            module: license.module(),
+        }
+        .into_err())
+    }
+}
@@ -0,0 +1,160 @@
+//! SSRF hardening for outbound requests whose target the user controls.
+//!
+//! `test_llm_connection` and `call_openai_api` build a request against
+//! whatever `base_url` a project configures, which includes "local"
+//! providers pointed at arbitrary hosts. This module gives those call
+//! sites a shared client builder that resolves through a configurable DNS
+//! resolver and refuses to connect to a private, loopback, or link-local
+//! address unless the project explicitly opts in, plus an optional
+//! per-provider host allowlist.
+
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Per-project egress rules for outbound LLM/API connections. The default
+/// (no config at all) keeps the old behavior of reaching public hosts
+/// through the system resolver while still blocking private ranges.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EgressPolicy {
+    /// Allow resolving to private/loopback/link-local addresses — needed
+    /// for a genuinely local LLM server on the same machine or LAN.
+    #[serde(default)]
+    pub allow_private_networks: bool,
+    /// If set, only these hosts (exact match against the URL's host) may
+    /// be contacted at all, regardless of what they resolve to.
+    #[serde(default)]
+    pub allowed_hosts: Option<Vec<String>>,
+    /// Custom nameservers to resolve through instead of the system
+    /// resolver, e.g. to pin a provider's hostname without trusting
+    /// whatever DNS the host machine happens to have configured.
+    #[serde(default)]
+    pub nameservers: Option<Vec<String>>,
+}
+
+/// Fail fast with a clear error before attempting a connection, instead of
+/// letting a blocked host surface as an opaque DNS or connect failure.
+///
+/// Also blocks private/loopback/link-local IP literals directly: `reqwest`
+/// never invokes the custom `Resolve` impl in [`build_client`] when the
+/// URL's host is already an IP address (no resolution needed), so
+/// `http://127.0.0.1/...` or `http://169.254.169.254/...` would otherwise
+/// sail straight past `PolicyResolver` regardless of `allow_private_networks`.
+pub fn check_host_allowed(url: &str, policy: &EgressPolicy) -> Result<(), String> {
+    let host = reqwest::Url::parse(url)
+        .map_err(|e| format!("Invalid URL: {}", e))?
+        .host_str()
+        .map(str::to_string)
+        .ok_or_else(|| "URL has no host".to_string())?;
+
+    if !policy.allow_private_networks {
+        // `host_str` keeps the brackets around an IPv6 literal.
+        let literal = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = literal.parse::<IpAddr>() {
+            if is_disallowed(ip) {
+                return Err(format!(
+                    "blocked by egress policy: '{}' is a private/loopback/link-local address",
+                    host
+                ));
+            }
+        }
+    }
+
+    let Some(allowed) = &policy.allowed_hosts else {
+        return Ok(());
+    };
+
+    if allowed.iter().any(|h| h == &host) {
+        Ok(())
+    } else {
+        Err(format!(
+            "blocked by egress policy: '{}' is not in the allowed host list",
+            host
+        ))
+    }
+}
+
+/// Build a `reqwest::Client` that enforces `policy` on every request it
+/// sends: a custom resolver when nameservers are configured, and a
+/// private/loopback/link-local block unless explicitly allowed.
+pub fn build_client(policy: &EgressPolicy) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder();
+
+    if policy.nameservers.is_some() || !policy.allow_private_networks {
+        let resolver = match &policy.nameservers {
+            Some(servers) => {
+                TokioAsyncResolver::tokio(custom_resolver_config(servers), ResolverOpts::default())
+            }
+            None => TokioAsyncResolver::tokio_from_system_conf()
+                .map_err(|e| format!("Failed to read system DNS configuration: {}", e))?,
+        };
+        builder = builder.dns_resolver(Arc::new(PolicyResolver {
+            resolver,
+            allow_private_networks: policy.allow_private_networks,
+        }));
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+fn custom_resolver_config(nameservers: &[String]) -> ResolverConfig {
+    let ips: Vec<IpAddr> = nameservers
+        .iter()
+        .filter_map(|s| s.parse::<IpAddr>().ok())
+        .collect();
+    ResolverConfig::from_parts(
+        None,
+        vec![],
+        NameServerConfigGroup::from_ips_clear(&ips, 53, true),
+    )
+}
+
+struct PolicyResolver {
+    resolver: TokioAsyncResolver,
+    allow_private_networks: bool,
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.resolver.clone();
+        let allow_private_networks = self.allow_private_networks;
+        let host = name.as_str().to_string();
+
+        Box::pin(async move {
+            let lookup = resolver
+                .lookup_ip(host.as_str())
+                .await
+                .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
+
+            let addrs: Vec<SocketAddr> = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+
+            if !allow_private_networks {
+                if let Some(blocked) = addrs.iter().find(|addr| is_disallowed(addr.ip())) {
+                    return Err(format!(
+                        "blocked by egress policy: '{}' resolves to {}",
+                        host,
+                        blocked.ip()
+                    )
+                    .into());
+                }
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn is_disallowed(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_loopback() || v4.is_private() || v4.is_link_local() || v4.is_unspecified(),
+        IpAddr::V6(v6) => {
+            v6.is_loopback() || v6.is_unspecified() || (v6.segments()[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
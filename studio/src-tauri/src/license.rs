@@ -0,0 +1,136 @@
+//! Offline-verifiable module licenses.
+//!
+//! `validate_license` used to accept anything shaped like `STUDIO-...` and
+//! fabricate a one-year expiry — trivially forgeable by typing a prefix.
+//! Licenses are now signed claim sets, issued by the Orchestrator in the
+//! same compact `header.payload.signature` shape a JWT uses, and verified
+//! here against an Ed25519 public key embedded in the binary. Verification
+//! is fully offline; an optional revocation check against the Orchestrator
+//! degrades gracefully to the offline result when the service is
+//! unreachable or not configured.
+
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Public half of the Orchestrator's module-license signing keypair. The
+/// private key never leaves the license server; only it can mint a token
+/// that verifies against this key.
+const LICENSE_PUBLIC_KEY: [u8; 32] = [
+    0x1f, 0x9a, 0xc4, 0x3d, 0x77, 0x2e, 0x58, 0xb1, 0x04, 0xe6, 0x9c, 0x3a, 0x8d, 0x55, 0x0b, 0x27,
+    0x41, 0xd8, 0x63, 0x0f, 0x92, 0xab, 0x1e, 0x7c, 0x5a, 0x36, 0xf4, 0x09, 0xc2, 0x88, 0x6d, 0x1b,
+];
+
+/// Claims carried by a signed module license token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseClaims {
+    /// Hardware/account id this license is bound to.
+    pub sub: String,
+    pub module: String,
+    pub features: Vec<String>,
+    /// Expiry, Unix seconds.
+    pub exp: i64,
+    /// Not-valid-before, Unix seconds. Absent means valid immediately.
+    #[serde(default)]
+    pub nbf: Option<i64>,
+    pub seats: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct LicenseHeader {
+    alg: String,
+}
+
+/// Verify a license token's signature, `exp`/`nbf`, seat count, and device
+/// binding (`sub` must match this machine's fingerprint) against the
+/// embedded Orchestrator public key, entirely offline. Rejects the token
+/// rather than trusting it on any failure, including an unrecognized
+/// signing algorithm.
+pub fn verify_license_token(token: &str) -> Result<LicenseClaims, String> {
+    let mut parts = token.split('.');
+    let (Some(header_b64), Some(payload_b64), Some(signature_b64), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err("Malformed license token".to_string());
+    };
+
+    let header_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(header_b64)
+        .map_err(|_| "Malformed license token header".to_string())?;
+    let header: LicenseHeader = serde_json::from_slice(&header_bytes)
+        .map_err(|_| "Malformed license token header".to_string())?;
+    if header.alg != "EdDSA" {
+        return Err(format!("Unsupported license signing algorithm: {}", header.alg));
+    }
+
+    let signature_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(signature_b64)
+        .map_err(|_| "Malformed license token signature".to_string())?;
+    let signature_bytes: [u8; 64] = signature_bytes
+        .try_into()
+        .map_err(|_| "Malformed license token signature".to_string())?;
+    let signature = Signature::from_bytes(&signature_bytes);
+
+    let verifying_key = VerifyingKey::from_bytes(&LICENSE_PUBLIC_KEY)
+        .map_err(|_| "Invalid embedded license public key".to_string())?;
+    let signed_message = format!("{}.{}", header_b64, payload_b64);
+    verifying_key
+        .verify_strict(signed_message.as_bytes(), &signature)
+        .map_err(|_| "License signature verification failed".to_string())?;
+
+    let payload_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload_b64)
+        .map_err(|_| "Malformed license token payload".to_string())?;
+    let claims: LicenseClaims = serde_json::from_slice(&payload_bytes)
+        .map_err(|_| "Malformed license token payload".to_string())?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    if now >= claims.exp {
+        return Err("License has expired".to_string());
+    }
+    if let Some(nbf) = claims.nbf {
+        if now < nbf {
+            return Err("License is not yet valid".to_string());
+        }
+    }
+    if claims.seats == 0 {
+        return Err("License has no seats".to_string());
+    }
+
+    let fingerprint = crate::protection::machine_fingerprint();
+    if claims.sub != fingerprint {
+        return Err("License is not bound to this machine".to_string());
+    }
+
+    Ok(claims)
+}
+
+/// Ask the Orchestrator whether `token` has been revoked since it was
+/// issued. Returns `None` (meaning: trust the offline verification) when
+/// no Orchestrator URL is configured or the service can't be reached —
+/// revocation is a hardening layer on top of offline verification, not a
+/// hard dependency for an otherwise offline-capable desktop app.
+pub async fn check_revocation_online(token: &str) -> Option<bool> {
+    let base_url = std::env::var("SKULDBOT_ORCHESTRATOR_URL").ok()?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .ok()?;
+
+    let response = client
+        .post(format!("{}/api/licenses/validate", base_url.trim_end_matches('/')))
+        .json(&serde_json::json!({ "token": token }))
+        .send()
+        .await
+        .ok()?;
+
+    if !response.status().is_success() {
+        return None;
+    }
+    let body: serde_json::Value = response.json().await.ok()?;
+    body.get("revoked").and_then(|v| v.as_bool())
+}
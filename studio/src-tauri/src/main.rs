@@ -1,12 +1,145 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::process::Command;
-use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use tauri::{Emitter, Manager};
+
+mod vault_backend;
+use vault_backend::{build_backend, SecretBackend, SecretBackendConfig};
+
+mod vault_session;
+use vault_session::VaultSessionStore;
+
+mod vault_broker;
+use vault_broker::CredentialBrokerStore;
+
+mod egress;
+use egress::EgressPolicy;
+
+mod protection;
+use protection::isolation::IsolationState;
+
+mod ai_streaming;
+
+mod license;
+
+mod capability;
+use capability::LicenseState;
+
+// ============================================================
+// Logging
+// ============================================================
+
+/// The currently active project's log file, if one has been opened/created
+/// yet. `None` means every log record is console-only (e.g. at startup,
+/// before a project is loaded). Held behind a `OnceLock` so the `fern`
+/// dispatch installed once in `init_logging` can keep writing to whatever
+/// file `set_project_log_file` last pointed it at.
+fn log_file_slot() -> &'static Mutex<Option<(PathBuf, fs::File)>> {
+    static SLOT: OnceLock<Mutex<Option<(PathBuf, fs::File)>>> = OnceLock::new();
+    SLOT.get_or_init(|| Mutex::new(None))
+}
+
+/// `fern` output target that forwards to whatever file `log_file_slot`
+/// currently holds, silently dropping writes when no project is open yet.
+struct ProjectLogWriter;
+
+impl std::io::Write for ProjectLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        use std::io::Write as _;
+        match log_file_slot().lock().unwrap().as_mut() {
+            Some((_, file)) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        use std::io::Write as _;
+        match log_file_slot().lock().unwrap().as_mut() {
+            Some((_, file)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn level_filter_from_setting(log_level: Option<&str>) -> log::LevelFilter {
+    match log_level.unwrap_or("INFO").to_uppercase().as_str() {
+        "TRACE" => log::LevelFilter::Trace,
+        "DEBUG" => log::LevelFilter::Debug,
+        "WARN" => log::LevelFilter::Warn,
+        "ERROR" => log::LevelFilter::Error,
+        _ => log::LevelFilter::Info,
+    }
+}
+
+/// Install the process-wide logger once at startup: console always, plus a
+/// file target that starts out empty and gets pointed at the active
+/// project's `.skuldbot/logs/` once one is opened (see
+/// `set_project_log_file`). Defaults to `Info` until a project's
+/// `ProjectSettings.log_level` says otherwise.
+fn init_logging() {
+    let dispatch = fern::Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "[{} {} {}] {}",
+                Utc::now().to_rfc3339(),
+                record.level(),
+                record.target(),
+                message
+            ))
+        })
+        // Filter only through `log::set_max_level`, so a project's level can
+        // later raise or lower it; a dispatch-level filter would cap it.
+        .level(log::LevelFilter::Trace)
+        .chain(std::io::stdout())
+        .chain(Box::new(ProjectLogWriter) as Box<dyn std::io::Write + Send>);
+
+    if let Err(e) = dispatch.apply() {
+        eprintln!("Failed to initialize logger: {e}");
+        return;
+    }
+    log::set_max_level(level_filter_from_setting(None));
+}
+
+/// Point the logger's file target at `<project_dir>/.skuldbot/logs/` and
+/// apply the project's configured level. The file is named for the day the
+/// project is opened (`studio-YYYY-MM-DD.log`) and stays in use for the
+/// rest of the session; it is not switched when the date changes.
+fn set_project_log_file(project_dir: &Path, log_level: Option<&str>) {
+    let log_dir = project_dir.join(".skuldbot").join("logs");
+    if let Err(e) = fs::create_dir_all(&log_dir) {
+        log::warn!("Failed to create log directory {}: {}", log_dir.display(), e);
+        return;
+    }
+
+    let log_path = log_dir.join(format!("studio-{}.log", Utc::now().format("%Y-%m-%d")));
+    match fs::OpenOptions::new().create(true).append(true).open(&log_path) {
+        Ok(file) => {
+            *log_file_slot().lock().unwrap() = Some((log_path, file));
+            log::set_max_level(level_filter_from_setting(log_level));
+        }
+        Err(e) => log::warn!("Failed to open log file {}: {}", log_path.display(), e),
+    }
+}
+
+/// Path to the log file the active project is currently writing to, for the
+/// UI to offer an "Open log" action.
+#[tauri::command]
+async fn get_log_file_path() -> Result<String, String> {
+    log_file_slot()
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|(path, _)| path.display().to_string())
+        .ok_or_else(|| "No project log file is open yet".to_string())
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct BotDSL {
@@ -29,14 +162,6 @@ struct CompileResult {
     bot_path: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-struct ExecutionResult {
-    success: bool,
-    message: String,
-    output: Option<String>,
-    logs: Vec<String>,
-}
-
 // ============================================================
 // Project System Types
 // ============================================================
@@ -124,10 +249,11 @@ struct CommandResult {
     data: Option<serde_json::Value>,
 }
 
-// Get the path to the engine directory
-fn get_engine_path() -> PathBuf {
-    // Try multiple paths to find the engine
-    let possible_paths = vec![
+// Candidate locations for the engine directory, checked in order. Kept as
+// its own function so `diagnose_environment` can report on every candidate,
+// not just the one that happened to resolve.
+fn engine_candidate_paths() -> Vec<PathBuf> {
+    vec![
         // Absolute path (most reliable for development)
         PathBuf::from("/Users/dubielvaldivia/Documents/khipus/skuldbot/engine"),
         // Relative from executable
@@ -145,11 +271,14 @@ fn get_engine_path() -> PathBuf {
         },
         // Relative path (development)
         PathBuf::from("../../engine"),
-    ];
+    ]
+}
 
-    for path in possible_paths {
+// Get the path to the engine directory
+fn get_engine_path() -> PathBuf {
+    for path in engine_candidate_paths() {
         if path.exists() && path.join(".venv").exists() {
-            println!("🔧 Engine found at: {}", path.display());
+            log::info!("🔧 Engine found at: {}", path.display());
             return path;
         }
     }
@@ -166,10 +295,10 @@ fn get_python_executable() -> String {
     // Use venv Python if available, otherwise fall back to system python
     if venv_python.exists() {
         let python_path = venv_python.to_string_lossy().to_string();
-        println!("🐍 Using venv Python: {}", python_path);
+        log::info!("🐍 Using venv Python: {}", python_path);
         python_path
     } else {
-        println!("⚠️  Venv not found at: {}, falling back to system Python", venv_python.display());
+        log::warn!("⚠️  Venv not found at: {}, falling back to system Python", venv_python.display());
         if Command::new("python3").arg("--version").output().is_ok() {
             "python3".to_string()
         } else {
@@ -178,18 +307,188 @@ fn get_python_executable() -> String {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum DiagnosticStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    name: String,
+    status: DiagnosticStatus,
+    detected: Option<String>,
+    remediation: Option<String>,
+}
+
+impl Diagnostic {
+    fn ok(name: &str, detected: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: DiagnosticStatus::Ok,
+            detected: Some(detected.into()),
+            remediation: None,
+        }
+    }
+
+    fn error(name: &str, remediation: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: DiagnosticStatus::Error,
+            detected: None,
+            remediation: Some(remediation.into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct EnvironmentReport {
+    healthy: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn run_python(python_exe: &str, code: &str) -> Option<String> {
+    Command::new(python_exe)
+        .arg("-c")
+        .arg(code)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+/// Inspect the engine install the way Tauri's own `info` tooling probes tool
+/// versions, so setup problems surface as a structured report the UI can
+/// render instead of an opaque failure the first time a bot is compiled.
 #[tauri::command]
-async fn compile_dsl(dsl: String) -> Result<CompileResult, String> {
-    println!("🔧 Compiling DSL...");
-    
+async fn diagnose_environment() -> Result<EnvironmentReport, String> {
+    let mut diagnostics = Vec::new();
+
+    // Engine path: which candidate (if any) resolved.
+    let mut resolved_engine_path = None;
+    for path in engine_candidate_paths() {
+        if path.exists() && path.join(".venv").exists() {
+            resolved_engine_path = Some(path);
+            break;
+        }
+    }
+    match &resolved_engine_path {
+        Some(path) => diagnostics.push(Diagnostic::ok("engine_path", path.display().to_string())),
+        None => diagnostics.push(Diagnostic::error(
+            "engine_path",
+            "No engine directory with a .venv was found in any candidate location. Run the engine's setup script to create one.",
+        )),
+    }
+
+    let Some(engine_path) = resolved_engine_path else {
+        return Ok(EnvironmentReport {
+            healthy: false,
+            diagnostics,
+        });
+    };
+
+    // .venv presence (redundant with engine_path once resolved, but reported
+    // separately since an engine dir can exist without one).
+    let venv_path = engine_path.join(".venv");
+    diagnostics.push(if venv_path.exists() {
+        Diagnostic::ok("venv", venv_path.display().to_string())
+    } else {
+        Diagnostic::error("venv", "Create a virtualenv at <engine>/.venv and install the engine's requirements.")
+    });
+
+    // Python interpreter + version.
+    let python_exe = get_python_executable();
+    match Command::new(&python_exe).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = if version.trim().is_empty() {
+                String::from_utf8_lossy(&output.stderr).trim().to_string()
+            } else {
+                version.trim().to_string()
+            };
+            diagnostics.push(Diagnostic::ok("python", format!("{python_exe} ({version})")));
+        }
+        _ => diagnostics.push(Diagnostic::error(
+            "python",
+            format!("Could not run '{python_exe} --version'. Check the venv is not corrupt."),
+        )),
+    }
+
+    // skuldbot package version.
+    let version_code = format!(
+        "import sys; sys.path.insert(0, '{}')\nfrom skuldbot import __version__\nprint(__version__)",
+        engine_path.display()
+    );
+    match run_python(&python_exe, &version_code) {
+        Some(version) => diagnostics.push(Diagnostic::ok("skuldbot_version", version)),
+        None => diagnostics.push(Diagnostic::error(
+            "skuldbot_version",
+            "Could not import skuldbot. Run `pip install -e .` inside the engine's venv.",
+        )),
+    }
+
+    // robot executable on the venv path.
+    let python_dir = PathBuf::from(&python_exe)
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_default();
+    let robot_exe = python_dir.join("robot");
+    let robot_exe_str = if robot_exe.exists() {
+        robot_exe.to_string_lossy().to_string()
+    } else {
+        "robot".to_string()
+    };
+    diagnostics.push(if robot_exe.exists() {
+        Diagnostic::ok("robot_executable", robot_exe_str.clone())
+    } else {
+        Diagnostic {
+            name: "robot_executable".to_string(),
+            status: DiagnosticStatus::Warning,
+            detected: Some("robot (not found next to venv python, falling back to PATH)".to_string()),
+            remediation: Some("Install Robot Framework into the engine's venv: pip install robotframework".to_string()),
+        }
+    });
+
+    // Robot Framework version.
+    match Command::new(&robot_exe_str).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            diagnostics.push(Diagnostic::ok("robot_framework_version", version));
+        }
+        _ => diagnostics.push(Diagnostic::error(
+            "robot_framework_version",
+            format!("Could not run '{robot_exe_str} --version'."),
+        )),
+    }
+
+    let healthy = diagnostics
+        .iter()
+        .all(|d| d.status != DiagnosticStatus::Error);
+
+    Ok(EnvironmentReport {
+        healthy,
+        diagnostics,
+    })
+}
+
+/// Compile a DSL JSON document to a bot directory on disk. Shared by the
+/// `compile_dsl` Tauri command and the `skuldbot compile` CLI subcommand.
+fn compile_dsl_impl(dsl: &str, output_dir: Option<&std::path::Path>) -> Result<CompileResult, String> {
+    log::info!("🔧 Compiling DSL...");
+
     let engine_path = get_engine_path();
     let python_exe = get_python_executable();
-    
+
     // Create a temporary file with the DSL
     let temp_dir = std::env::temp_dir();
     let dsl_file = temp_dir.join("bot_dsl.json");
-    std::fs::write(&dsl_file, &dsl).map_err(|e| e.to_string())?;
-    
+    std::fs::write(&dsl_file, dsl).map_err(|e| e.to_string())?;
+    let output_dir = output_dir
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| temp_dir.join("bots"));
+
     // Run the compiler
     let output = Command::new(&python_exe)
         .arg("-c")
@@ -210,15 +509,15 @@ print(str(bot_dir))
 "#,
             engine_path.display(),
             dsl_file.display(),
-            temp_dir.join("bots").display()
+            output_dir.display()
         ))
         .output()
         .map_err(|e| format!("Failed to execute Python: {}", e))?;
-    
+
     if output.status.success() {
         let bot_path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        println!("✅ Bot compiled to: {}", bot_path);
-        
+        log::info!("✅ Bot compiled to: {}", bot_path);
+
         Ok(CompileResult {
             success: true,
             message: "Bot compilado exitosamente".to_string(),
@@ -226,26 +525,128 @@ print(str(bot_dir))
         })
     } else {
         let error = String::from_utf8_lossy(&output.stderr);
-        println!("❌ Compilation error: {}", error);
-        
+        log::error!("❌ Compilation error: {}", error);
+
         Err(format!("Error al compilar: {}", error))
     }
 }
 
 #[tauri::command]
-async fn run_bot(dsl: String) -> Result<ExecutionResult, String> {
-    println!("▶️  Running bot...");
-    
+async fn compile_dsl(dsl: String) -> Result<CompileResult, String> {
+    compile_dsl_impl(&dsl, None)
+}
+
+/// Live child handles for in-flight bot executions, keyed by execution id,
+/// so `cancel_execution` can reach in and kill one mid-run.
+struct ExecutionRegistry(Mutex<HashMap<String, Child>>);
+
+#[derive(Debug, Clone, Serialize)]
+struct BotLogEvent {
+    execution_id: String,
+    bot_id: String,
+    stream: &'static str,
+    line: String,
+    level: Option<&'static str>,
+    ts: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BotExecutionDone {
+    execution_id: String,
+    success: bool,
+    message: String,
+}
+
+/// Best-effort extraction of a Robot Framework log level from a console
+/// line, so the UI can color/filter without re-parsing the whole line.
+fn robot_log_level(line: &str) -> Option<&'static str> {
+    const LEVELS: [&str; 5] = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+    let token = line.split_whitespace().find(|w| LEVELS.contains(w))?;
+    LEVELS.iter().find(|&&l| l == token).copied()
+}
+
+/// Map a `ProjectSettings.log_level` value onto one of Robot Framework's
+/// `--loglevel` values (`TRACE`/`DEBUG`/`INFO`/`WARN`/`NONE`), so console
+/// verbosity matches what the project has configured instead of always
+/// running at `DEBUG`.
+fn robot_loglevel_arg(log_level: Option<&str>) -> &'static str {
+    match log_level.unwrap_or("INFO").to_uppercase().as_str() {
+        "TRACE" => "TRACE",
+        "DEBUG" => "DEBUG",
+        "WARN" | "WARNING" => "WARN",
+        "ERROR" | "NONE" => "NONE",
+        _ => "INFO",
+    }
+}
+
+fn spawn_bot_log_reader<R>(
+    app: tauri::AppHandle,
+    execution_id: String,
+    bot_id: String,
+    stream: R,
+    stream_name: &'static str,
+) where
+    R: std::io::Read + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let reader = std::io::BufReader::new(stream);
+        for line in std::io::BufRead::lines(reader) {
+            let Ok(line) = line else { break };
+            if line.is_empty() {
+                continue;
+            }
+            let _ = app.emit(
+                "bot://log",
+                BotLogEvent {
+                    execution_id: execution_id.clone(),
+                    bot_id: bot_id.clone(),
+                    stream: stream_name,
+                    level: robot_log_level(&line),
+                    line,
+                    ts: Utc::now().to_rfc3339(),
+                },
+            );
+        }
+    });
+}
+
+/// Spawn the bot, returning its execution id immediately. Output streams to
+/// the frontend via `bot://log` events as it's produced, and a final
+/// `bot://done` event reports the outcome once the process exits.
+#[tauri::command]
+async fn run_bot(
+    app: tauri::AppHandle,
+    dsl: String,
+    log_level: Option<String>,
+    vault_path: Option<String>,
+    allowed_secrets: Option<Vec<String>>,
+) -> Result<String, String> {
+    log::info!("▶️  Running bot...");
+
     let engine_path = get_engine_path();
     let python_exe = get_python_executable();
-    
+    let robot_loglevel = robot_loglevel_arg(log_level.as_deref());
+
+    // If the project's vault is unlocked, give this bot a token for the
+    // credential broker instead of the master password, so it can fetch
+    // only the secrets it's whitelisted for, for as long as it runs.
+    let broker_grant = vault_path.as_deref().and_then(|path| {
+        app.state::<CredentialBrokerStore>()
+            .issue_token(path, allowed_secrets)
+    });
+
+    let bot_id = serde_json::from_str::<BotDSL>(&dsl)
+        .map(|b| b.bot.id)
+        .unwrap_or_else(|_| "unknown".to_string());
+    let execution_id = Uuid::new_v4().to_string();
+
     // Create a temporary file with the DSL
     let temp_dir = std::env::temp_dir();
-    let dsl_file = temp_dir.join("bot_run_dsl.json");
+    let dsl_file = temp_dir.join(format!("bot_run_{}.json", execution_id));
     std::fs::write(&dsl_file, &dsl).map_err(|e| e.to_string())?;
-    
-    // Run the bot
-    let output = Command::new(&python_exe)
+
+    let mut child = Command::new(&python_exe)
+        .arg("-u") // unbuffered, so output reaches us as it's produced
         .arg("-c")
         .arg(format!(
             r#"
@@ -274,7 +675,6 @@ except Exception as e:
     print(f'ERROR: {{e}}')
     sys.exit(1)
 
-# Execute with captured output
 main_robot = Path(bot_dir) / "main.robot"
 output_path = Path(bot_dir) / "output"
 output_path.mkdir(exist_ok=True)
@@ -283,62 +683,199 @@ output_path.mkdir(exist_ok=True)
 python_dir = Path(sys.executable).parent
 robot_exe = str(python_dir / "robot") if (python_dir / "robot").exists() else "robot"
 
-# Run robot and capture output
+# Inherit stdout/stderr instead of capturing, so robot's own output streams
+# through us line by line rather than arriving all at once at the end.
 result = subprocess.run(
-    [robot_exe, "--loglevel", "DEBUG", "--outputdir", str(output_path), "--consolecolors", "off", str(main_robot)],
-    capture_output=True,
-    text=True,
+    [robot_exe, "--loglevel", "{}", "--outputdir", str(output_path), "--consolecolors", "off", str(main_robot)],
     cwd=bot_dir
 )
 
-# Print robot output (this is what shows in console)
-for line in result.stdout.split('\n'):
-    if line.strip():
-        print(line)
-
 print('STATUS:', 'success' if result.returncode == 0 else 'failed')
-print('SUCCESS:', result.returncode == 0)
-if result.stderr:
-    print('STDERR:', result.stderr)
+sys.exit(0 if result.returncode == 0 else 1)
 "#,
             engine_path.display(),
             dsl_file.display(),
-            temp_dir.join("bots_run").display()
+            temp_dir.join("bots_run").display(),
+            robot_loglevel
         ))
-        .output()
+        .envs(broker_grant.iter().flat_map(|(endpoint, token)| {
+            [
+                ("SKULDBOT_VAULT_SOCKET", endpoint.clone()),
+                ("SKULDBOT_VAULT_TOKEN", token.clone()),
+            ]
+        }))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
         .map_err(|e| format!("Failed to execute Python: {}", e))?;
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    
-    println!("📝 Output: {}", stdout);
-    if !stderr.is_empty() {
-        println!("⚠️  Stderr: {}", stderr);
-    }
-    
-    if output.status.success() {
-        Ok(ExecutionResult {
-            success: true,
-            message: "Bot ejecutado".to_string(),
-            output: Some(stdout.to_string()),
-            logs: stdout.lines().map(|s| s.to_string()).collect(),
-        })
-    } else {
-        Err(format!("Error al ejecutar: {}\n{}", stdout, stderr))
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_bot_log_reader(app.clone(), execution_id.clone(), bot_id.clone(), stdout, "stdout");
     }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_bot_log_reader(app.clone(), execution_id.clone(), bot_id.clone(), stderr, "stderr");
+    }
+
+    app.state::<ExecutionRegistry>()
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(execution_id.clone(), child);
+
+    // Poll for completion on a background thread so the command can return
+    // the execution id immediately.
+    let app_for_wait = app.clone();
+    let execution_id_wait = execution_id.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        let outcome = {
+            let registry = app_for_wait.state::<ExecutionRegistry>();
+            let mut executions = registry.0.lock().unwrap();
+            match executions.get_mut(&execution_id_wait) {
+                Some(child) => match child.try_wait() {
+                    Ok(Some(status)) => {
+                        executions.remove(&execution_id_wait);
+                        Some(status.success())
+                    }
+                    Ok(None) => None,
+                    Err(_) => {
+                        executions.remove(&execution_id_wait);
+                        Some(false)
+                    }
+                },
+                // Already removed, e.g. by cancel_execution.
+                None => Some(false),
+            }
+        };
+
+        if let Some(success) = outcome {
+            let _ = app_for_wait.emit(
+                "bot://done",
+                BotExecutionDone {
+                    execution_id: execution_id_wait.clone(),
+                    success,
+                    message: if success {
+                        "Bot ejecutado".to_string()
+                    } else {
+                        "Ejecución fallida".to_string()
+                    },
+                },
+            );
+            break;
+        }
+    });
+
+    Ok(execution_id)
 }
 
+/// Kill an in-flight bot execution started by `run_bot`.
 #[tauri::command]
-async fn validate_dsl(dsl: String) -> Result<bool, String> {
-    println!("✓ Validating DSL...");
-    
+async fn cancel_execution(
+    registry: tauri::State<'_, ExecutionRegistry>,
+    execution_id: String,
+) -> Result<(), String> {
+    let mut executions = registry.0.lock().map_err(|e| e.to_string())?;
+    match executions.remove(&execution_id) {
+        Some(mut child) => {
+            child.kill().map_err(|e| e.to_string())?;
+            // `Child::drop` doesn't reap the process, and it's already out
+            // of the registry so `run_bot`'s poller won't reap it either
+            // (it sees `None` and stops polling) — without an explicit
+            // `wait()` the killed process stays a zombie until Studio
+            // exits. Reap it on a short-lived thread so this command
+            // returns immediately.
+            std::thread::spawn(move || {
+                let _ = child.wait();
+            });
+            Ok(())
+        }
+        None => Err(format!("No running execution with id {execution_id}")),
+    }
+}
+
+/// Compile and execute a DSL document to completion, inheriting the caller's
+/// stdout/stderr instead of streaming Tauri events. Used by the `skuldbot
+/// run` CLI subcommand, which needs a real exit code and no event loop.
+fn run_bot_blocking(dsl: &str, log_level: Option<&str>) -> Result<bool, String> {
+    let engine_path = get_engine_path();
+    let python_exe = get_python_executable();
+    let robot_loglevel = robot_loglevel_arg(log_level);
+
+    let temp_dir = std::env::temp_dir();
+    let dsl_file = temp_dir.join("bot_run_cli.json");
+    std::fs::write(&dsl_file, dsl).map_err(|e| e.to_string())?;
+
+    let status = Command::new(&python_exe)
+        .arg("-u")
+        .arg("-c")
+        .arg(format!(
+            r#"
+import sys
+sys.path.insert(0, '{}')
+import json
+import subprocess
+from pathlib import Path
+from skuldbot import Compiler, Executor, ExecutionMode
+from skuldbot.dsl.validator import ValidationError
+
+with open('{}', 'r') as f:
+    dsl = json.load(f)
+
+try:
+    compiler = Compiler()
+    output_dir = '{}'
+    bot_dir = compiler.compile_to_disk(dsl, output_dir)
+except ValidationError as e:
+    print('ERROR: Validation failed')
+    for err in e.errors:
+        print(f'  - {{err}}')
+    sys.exit(1)
+except Exception as e:
+    print(f'ERROR: {{e}}')
+    sys.exit(1)
+
+main_robot = Path(bot_dir) / "main.robot"
+output_path = Path(bot_dir) / "output"
+output_path.mkdir(exist_ok=True)
+
+python_dir = Path(sys.executable).parent
+robot_exe = str(python_dir / "robot") if (python_dir / "robot").exists() else "robot"
+
+result = subprocess.run(
+    [robot_exe, "--loglevel", "{}", "--outputdir", str(output_path), "--consolecolors", "off", str(main_robot)],
+    cwd=bot_dir
+)
+
+print('STATUS:', 'success' if result.returncode == 0 else 'failed')
+sys.exit(0 if result.returncode == 0 else 1)
+"#,
+            engine_path.display(),
+            dsl_file.display(),
+            temp_dir.join("bots_run").display(),
+            robot_loglevel
+        ))
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|e| format!("Failed to execute Python: {}", e))?;
+
+    Ok(status.success())
+}
+
+/// Validate a DSL JSON document against the engine's validator. Shared by
+/// the `validate_dsl` Tauri command and the `skuldbot validate` CLI
+/// subcommand.
+fn validate_dsl_impl(dsl: &str) -> Result<bool, String> {
+    log::info!("✓ Validating DSL...");
+
     let engine_path = get_engine_path();
     let python_exe = get_python_executable();
-    
+
     let temp_dir = std::env::temp_dir();
     let dsl_file = temp_dir.join("bot_validate_dsl.json");
-    std::fs::write(&dsl_file, &dsl).map_err(|e| e.to_string())?;
-    
+    std::fs::write(&dsl_file, dsl).map_err(|e| e.to_string())?;
+
     let output = Command::new(&python_exe)
         .arg("-c")
         .arg(format!(
@@ -364,30 +901,35 @@ except Exception as e:
         ))
         .output()
         .map_err(|e| format!("Failed to execute Python: {}", e))?;
-    
+
     if output.status.success() {
-        println!("✅ DSL is valid");
+        log::info!("✅ DSL is valid");
         Ok(true)
     } else {
         let error = String::from_utf8_lossy(&output.stderr);
-        println!("❌ DSL is invalid: {}", error);
+        log::error!("❌ DSL is invalid: {}", error);
         Err(error.to_string())
     }
 }
 
+#[tauri::command]
+async fn validate_dsl(dsl: String) -> Result<bool, String> {
+    validate_dsl_impl(&dsl)
+}
+
 #[tauri::command]
 async fn save_project(path: String, data: String) -> Result<(), String> {
-    println!("💾 Saving project to: {}", path);
+    log::info!("💾 Saving project to: {}", path);
     std::fs::write(&path, data).map_err(|e| e.to_string())?;
-    println!("✅ Project saved");
+    log::info!("✅ Project saved");
     Ok(())
 }
 
 #[tauri::command]
 async fn load_project(path: String) -> Result<String, String> {
-    println!("📂 Loading project from: {}", path);
+    log::info!("📂 Loading project from: {}", path);
     let data = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    println!("✅ Project loaded");
+    log::info!("✅ Project loaded");
     Ok(data)
 }
 
@@ -429,7 +971,7 @@ fn get_recent_projects_path() -> PathBuf {
 
 #[tauri::command]
 async fn create_project(path: String, name: String, description: Option<String>) -> Result<ProjectManifest, String> {
-    println!("📁 Creating project: {} at {}", name, path);
+    log::info!("📁 Creating project: {} at {}", name, path);
 
     let project_path = PathBuf::from(&path);
 
@@ -518,13 +1060,15 @@ Thumbs.db
     // Add to recent projects
     let _ = add_recent_project_internal(&path, &name).await;
 
-    println!("✅ Project created: {}", manifest_path.display());
+    set_project_log_file(&project_path, manifest.settings.log_level.as_deref());
+
+    log::info!("✅ Project created: {}", manifest_path.display());
     Ok(manifest)
 }
 
 #[tauri::command]
 async fn open_project(path: String) -> Result<ProjectManifest, String> {
-    println!("📂 Opening project: {}", path);
+    log::info!("📂 Opening project: {}", path);
 
     let project_path = PathBuf::from(&path);
     let manifest_path = if project_path.extension().map_or(false, |e| e == "skuld") {
@@ -547,13 +1091,15 @@ async fn open_project(path: String) -> Result<ProjectManifest, String> {
     let project_dir = manifest_path.parent().unwrap().to_string_lossy().to_string();
     let _ = add_recent_project_internal(&project_dir, &manifest.project.name).await;
 
-    println!("✅ Project opened: {}", manifest.project.name);
+    set_project_log_file(manifest_path.parent().unwrap(), manifest.settings.log_level.as_deref());
+
+    log::info!("✅ Project opened: {}", manifest.project.name);
     Ok(manifest)
 }
 
 #[tauri::command]
 async fn save_project_manifest(path: String, manifest: ProjectManifest) -> Result<(), String> {
-    println!("💾 Saving project manifest: {}", path);
+    log::info!("💾 Saving project manifest: {}", path);
 
     let mut updated_manifest = manifest;
     updated_manifest.project.updated = Utc::now().to_rfc3339();
@@ -562,7 +1108,7 @@ async fn save_project_manifest(path: String, manifest: ProjectManifest) -> Resul
         .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
     fs::write(&path, manifest_json).map_err(|e| format!("Failed to write manifest: {}", e))?;
 
-    println!("✅ Manifest saved");
+    log::info!("✅ Manifest saved");
     Ok(())
 }
 
@@ -572,7 +1118,7 @@ async fn save_project_manifest(path: String, manifest: ProjectManifest) -> Resul
 
 #[tauri::command]
 async fn create_bot(project_path: String, name: String, description: Option<String>) -> Result<BotReference, String> {
-    println!("🤖 Creating bot: {} in {}", name, project_path);
+    log::info!("🤖 Creating bot: {} in {}", name, project_path);
 
     let project_dir = PathBuf::from(&project_path);
     let bot_id = Uuid::new_v4().to_string();
@@ -613,13 +1159,13 @@ async fn create_bot(project_path: String, name: String, description: Option<Stri
         updated: now,
     };
 
-    println!("✅ Bot created: {}", bot_dir.display());
+    log::info!("✅ Bot created: {}", bot_dir.display());
     Ok(bot_ref)
 }
 
 #[tauri::command]
 async fn load_bot(bot_path: String) -> Result<serde_json::Value, String> {
-    println!("📂 Loading bot: {}", bot_path);
+    log::info!("📂 Loading bot: {}", bot_path);
 
     let bot_json_path = PathBuf::from(&bot_path).join("bot.json");
     if !bot_json_path.exists() {
@@ -632,13 +1178,13 @@ async fn load_bot(bot_path: String) -> Result<serde_json::Value, String> {
     let bot: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse bot file: {}", e))?;
 
-    println!("✅ Bot loaded");
+    log::info!("✅ Bot loaded");
     Ok(bot)
 }
 
 #[tauri::command]
 async fn save_bot(bot_path: String, dsl: String) -> Result<(), String> {
-    println!("💾 Saving bot: {}", bot_path);
+    log::info!("💾 Saving bot: {}", bot_path);
 
     let bot_dir = PathBuf::from(&bot_path);
 
@@ -652,20 +1198,20 @@ async fn save_bot(bot_path: String, dsl: String) -> Result<(), String> {
     let bot_json_path = bot_dir.join("bot.json");
     fs::write(&bot_json_path, &dsl).map_err(|e| format!("Failed to write bot file: {}", e))?;
 
-    println!("✅ Bot saved");
+    log::info!("✅ Bot saved");
     Ok(())
 }
 
 #[tauri::command]
 async fn delete_bot(bot_path: String) -> Result<(), String> {
-    println!("🗑️ Deleting bot: {}", bot_path);
+    log::info!("🗑️ Deleting bot: {}", bot_path);
 
     let bot_dir = PathBuf::from(&bot_path);
     if bot_dir.exists() {
         fs::remove_dir_all(&bot_dir).map_err(|e| format!("Failed to delete bot: {}", e))?;
     }
 
-    println!("✅ Bot deleted");
+    log::info!("✅ Bot deleted");
     Ok(())
 }
 
@@ -675,7 +1221,7 @@ async fn delete_bot(bot_path: String) -> Result<(), String> {
 
 #[tauri::command]
 async fn save_bot_version(bot_path: String, dsl: String, description: Option<String>) -> Result<String, String> {
-    println!("📸 Saving bot version: {}", bot_path);
+    log::info!("📸 Saving bot version: {}", bot_path);
 
     let history_dir = PathBuf::from(&bot_path).join(".history");
     fs::create_dir_all(&history_dir).map_err(|e| format!("Failed to create history directory: {}", e))?;
@@ -694,13 +1240,13 @@ async fn save_bot_version(bot_path: String, dsl: String, description: Option<Str
     fs::write(&version_file, serde_json::to_string_pretty(&version_data).unwrap())
         .map_err(|e| format!("Failed to write version file: {}", e))?;
 
-    println!("✅ Version saved: {}", version_id);
+    log::info!("✅ Version saved: {}", version_id);
     Ok(version_id)
 }
 
 #[tauri::command]
 async fn list_bot_versions(bot_path: String) -> Result<Vec<serde_json::Value>, String> {
-    println!("📋 Listing bot versions: {}", bot_path);
+    log::info!("📋 Listing bot versions: {}", bot_path);
 
     let history_dir = PathBuf::from(&bot_path).join(".history");
     if !history_dir.exists() {
@@ -736,11 +1282,9 @@ async fn list_bot_versions(bot_path: String) -> Result<Vec<serde_json::Value>, S
     Ok(versions)
 }
 
-#[tauri::command]
-async fn load_bot_version(bot_path: String, version_id: String) -> Result<serde_json::Value, String> {
-    println!("📂 Loading bot version: {} - {}", bot_path, version_id);
-
-    let version_file = PathBuf::from(&bot_path).join(".history").join(format!("{}.json", version_id));
+/// Load a `.history/<version_id>.json` entry's stored DSL.
+fn load_history_version_dsl(bot_path: &str, version_id: &str) -> Result<serde_json::Value, String> {
+    let version_file = PathBuf::from(bot_path).join(".history").join(format!("{}.json", version_id));
     if !version_file.exists() {
         return Err(format!("Version not found: {}", version_id));
     }
@@ -754,9 +1298,139 @@ async fn load_bot_version(bot_path: String, version_id: String) -> Result<serde_
     Ok(version.get("dsl").cloned().unwrap_or(serde_json::json!({})))
 }
 
+#[tauri::command]
+async fn load_bot_version(bot_path: String, version_id: String) -> Result<serde_json::Value, String> {
+    log::info!("📂 Loading bot version: {} - {}", bot_path, version_id);
+    load_history_version_dsl(&bot_path, &version_id)
+}
+
+/// Index a DSL `nodes` array by each node's `id` field. Nodes without an
+/// `id` are skipped since they can't be diffed or restored individually.
+fn index_nodes_by_id(nodes: &[serde_json::Value]) -> HashMap<String, &serde_json::Value> {
+    nodes
+        .iter()
+        .filter_map(|n| n.get("id").and_then(|v| v.as_str()).map(|id| (id.to_string(), n)))
+        .collect()
+}
+
+/// Shallow field-by-field diff of two JSON objects, reporting `{from, to}`
+/// for every key whose value differs between them.
+fn diff_object_fields(from: &serde_json::Value, to: &serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    let empty = serde_json::Map::new();
+    let from_obj = from.as_object().unwrap_or(&empty);
+    let to_obj = to.as_object().unwrap_or(&empty);
+
+    let mut keys: std::collections::BTreeSet<&String> = from_obj.keys().collect();
+    keys.extend(to_obj.keys());
+
+    let mut changes = serde_json::Map::new();
+    for key in keys {
+        let from_value = from_obj.get(key).cloned().unwrap_or(serde_json::Value::Null);
+        let to_value = to_obj.get(key).cloned().unwrap_or(serde_json::Value::Null);
+        if from_value != to_value {
+            changes.insert(key.clone(), serde_json::json!({ "from": from_value, "to": to_value }));
+        }
+    }
+    changes
+}
+
+/// Node-level semantic diff between two saved versions of a bot, instead of
+/// the all-or-nothing full-DSL comparison the UI would otherwise have to do
+/// itself.
+#[tauri::command]
+async fn diff_bot_versions(bot_path: String, from_id: String, to_id: String) -> Result<serde_json::Value, String> {
+    let from_dsl = load_history_version_dsl(&bot_path, &from_id)?;
+    let to_dsl = load_history_version_dsl(&bot_path, &to_id)?;
+
+    let empty_nodes = vec![];
+    let from_nodes = from_dsl.get("nodes").and_then(|v| v.as_array()).unwrap_or(&empty_nodes);
+    let to_nodes = to_dsl.get("nodes").and_then(|v| v.as_array()).unwrap_or(&empty_nodes);
+
+    let from_map = index_nodes_by_id(from_nodes);
+    let to_map = index_nodes_by_id(to_nodes);
+
+    let mut added = vec![];
+    let mut removed = vec![];
+    let mut modified = vec![];
+
+    for (id, node) in &to_map {
+        if !from_map.contains_key(id) {
+            added.push((*node).clone());
+        }
+    }
+    for (id, from_node) in &from_map {
+        match to_map.get(id) {
+            None => removed.push((*from_node).clone()),
+            Some(to_node) => {
+                let field_changes = diff_object_fields(from_node, to_node);
+                if !field_changes.is_empty() {
+                    modified.push(serde_json::json!({ "id": id, "field_changes": field_changes }));
+                }
+            }
+        }
+    }
+
+    let empty_vars = serde_json::json!({});
+    let variables = diff_object_fields(
+        from_dsl.get("variables").unwrap_or(&empty_vars),
+        to_dsl.get("variables").unwrap_or(&empty_vars),
+    );
+
+    Ok(serde_json::json!({
+        "added": added,
+        "removed": removed,
+        "modified": modified,
+        "variables": variables,
+    }))
+}
+
+/// Restore only the listed node ids from `version_id` into the bot's current
+/// `bot.json`, leaving every other node (and any unrelated edits) untouched.
+#[tauri::command]
+async fn restore_nodes(bot_path: String, version_id: String, node_ids: Vec<String>) -> Result<(), String> {
+    log::info!("⏪ Restoring {} node(s) from version {} into {}", node_ids.len(), version_id, bot_path);
+
+    let version_dsl = load_history_version_dsl(&bot_path, &version_id)?;
+    let empty_nodes = vec![];
+    let version_nodes = version_dsl.get("nodes").and_then(|v| v.as_array()).unwrap_or(&empty_nodes);
+    let version_map = index_nodes_by_id(version_nodes);
+
+    let bot_json_path = PathBuf::from(&bot_path).join("bot.json");
+    let current_content = fs::read_to_string(&bot_json_path)
+        .map_err(|e| format!("Failed to read bot file: {}", e))?;
+    let mut current: serde_json::Value = serde_json::from_str(&current_content)
+        .map_err(|e| format!("Failed to parse bot file: {}", e))?;
+
+    let mut current_nodes = current
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    for node_id in &node_ids {
+        let Some(restored_node) = version_map.get(node_id.as_str()) else {
+            continue;
+        };
+        match current_nodes
+            .iter()
+            .position(|n| n.get("id").and_then(|v| v.as_str()) == Some(node_id.as_str()))
+        {
+            Some(pos) => current_nodes[pos] = (*restored_node).clone(),
+            None => current_nodes.push((*restored_node).clone()),
+        }
+    }
+
+    current["nodes"] = serde_json::Value::Array(current_nodes);
+    fs::write(&bot_json_path, serde_json::to_string_pretty(&current).unwrap())
+        .map_err(|e| format!("Failed to write bot file: {}", e))?;
+
+    log::info!("✅ Restored nodes");
+    Ok(())
+}
+
 #[tauri::command]
 async fn cleanup_old_versions(bot_path: String, max_versions: u32) -> Result<u32, String> {
-    println!("🧹 Cleaning up old versions: {} (max: {})", bot_path, max_versions);
+    log::info!("🧹 Cleaning up old versions: {} (max: {})", bot_path, max_versions);
 
     let history_dir = PathBuf::from(&bot_path).join(".history");
     if !history_dir.exists() {
@@ -785,7 +1459,7 @@ async fn cleanup_old_versions(bot_path: String, max_versions: u32) -> Result<u32
         version_files.remove(0);
     }
 
-    println!("✅ Cleaned up {} old versions", deleted);
+    log::info!("✅ Cleaned up {} old versions", deleted);
     Ok(deleted)
 }
 
@@ -795,7 +1469,7 @@ async fn cleanup_old_versions(bot_path: String, max_versions: u32) -> Result<u32
 
 #[tauri::command]
 async fn list_assets(assets_path: String) -> Result<Vec<FileInfo>, String> {
-    println!("📂 Listing assets: {}", assets_path);
+    log::info!("📂 Listing assets: {}", assets_path);
 
     let assets_dir = PathBuf::from(&assets_path);
     if !assets_dir.exists() {
@@ -824,7 +1498,7 @@ async fn list_assets(assets_path: String) -> Result<Vec<FileInfo>, String> {
 
 #[tauri::command]
 async fn copy_asset(source: String, destination: String) -> Result<(), String> {
-    println!("📋 Copying asset: {} -> {}", source, destination);
+    log::info!("📋 Copying asset: {} -> {}", source, destination);
 
     let dest_path = PathBuf::from(&destination);
     if let Some(parent) = dest_path.parent() {
@@ -833,13 +1507,13 @@ async fn copy_asset(source: String, destination: String) -> Result<(), String> {
 
     fs::copy(&source, &destination).map_err(|e| format!("Failed to copy asset: {}", e))?;
 
-    println!("✅ Asset copied");
+    log::info!("✅ Asset copied");
     Ok(())
 }
 
 #[tauri::command]
 async fn delete_asset(path: String) -> Result<(), String> {
-    println!("🗑️ Deleting asset: {}", path);
+    log::info!("🗑️ Deleting asset: {}", path);
 
     let asset_path = PathBuf::from(&path);
     if asset_path.is_dir() {
@@ -848,7 +1522,7 @@ async fn delete_asset(path: String) -> Result<(), String> {
         fs::remove_file(&asset_path).map_err(|e| e.to_string())?;
     }
 
-    println!("✅ Asset deleted");
+    log::info!("✅ Asset deleted");
     Ok(())
 }
 
@@ -892,7 +1566,7 @@ async fn add_recent_project_internal(path: &str, name: &str) -> Result<(), Strin
 
 #[tauri::command]
 async fn get_recent_projects() -> Result<Vec<RecentProject>, String> {
-    println!("📋 Getting recent projects");
+    log::info!("📋 Getting recent projects");
 
     let recent_path = get_recent_projects_path();
 
@@ -919,7 +1593,7 @@ async fn add_recent_project(path: String, name: String) -> Result<(), String> {
 
 #[tauri::command]
 async fn remove_recent_project(path: String) -> Result<(), String> {
-    println!("🗑️ Removing from recent: {}", path);
+    log::info!("🗑️ Removing from recent: {}", path);
 
     let recent_path = get_recent_projects_path();
 
@@ -942,12 +1616,12 @@ async fn remove_recent_project(path: String) -> Result<(), String> {
 // Vault Commands (Local Vault Management)
 // ============================================================
 
-#[derive(Debug, Serialize, Deserialize)]
-struct VaultSecret {
-    name: String,
-    description: Option<String>,
-    created_at: Option<String>,
-    updated_at: Option<String>,
+/// Build whichever `SecretBackend` a project is configured for. `backend`
+/// comes from the frontend as the project's `secretBackend` setting,
+/// serialized straight from `SecretBackendConfig`; omitting it keeps the
+/// original local-file vault behavior.
+fn resolve_backend(path: &str, backend: Option<SecretBackendConfig>) -> Box<dyn SecretBackend> {
+    build_backend(backend.as_ref(), get_engine_path(), get_python_executable(), path.to_string())
 }
 
 #[tauri::command]
@@ -957,292 +1631,277 @@ async fn vault_exists(path: String) -> Result<bool, String> {
 }
 
 #[tauri::command]
-async fn vault_is_unlocked(path: String) -> Result<bool, String> {
-    // For now, we can't check unlock status without trying to unlock
-    // Return false to force unlock
-    Ok(false)
+async fn vault_is_unlocked(path: String, session: tauri::State<'_, VaultSessionStore>) -> Result<bool, String> {
+    Ok(session.is_unlocked(&path))
 }
 
 #[tauri::command]
-async fn vault_create(password: String, path: String) -> Result<bool, String> {
-    println!("Creating vault at: {}", path);
+async fn vault_create(password: String, path: String, backend: Option<SecretBackendConfig>) -> Result<bool, String> {
+    log::info!("Creating vault at: {}", path);
 
-    let engine_path = get_engine_path();
-    let python_exe = get_python_executable();
-
-    let output = Command::new(&python_exe)
-        .arg("-c")
-        .arg(format!(
-            r#"
-import sys
-sys.path.insert(0, '{}')
-from skuldbot.libs.local_vault import LocalVault
+    let mut backend_instance = resolve_backend(&path, backend);
+    backend_instance.create(&password)?;
 
-vault = LocalVault('{}')
-vault.create('{}')
-print('OK')
-"#,
-            engine_path.display(),
-            path.replace("'", "\\'"),
-            password.replace("'", "\\'")
-        ))
-        .output()
-        .map_err(|e| format!("Failed to execute Python: {}", e))?;
+    log::info!("Vault created successfully");
+    Ok(true)
+}
 
-    if output.status.success() {
-        println!("Vault created successfully");
-        Ok(true)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to create vault: {}", error))
-    }
+/// Error returned when a command needs an unlocked session that isn't
+/// there — either it was never opened, or it went idle and auto-locked.
+fn vault_locked_err() -> String {
+    "Vault is locked. Call vault_unlock first.".to_string()
 }
 
 #[tauri::command]
-async fn vault_unlock(password: String, path: String) -> Result<bool, String> {
-    println!("Unlocking vault at: {}", path);
-
-    let engine_path = get_engine_path();
-    let python_exe = get_python_executable();
+async fn vault_unlock(
+    password: String,
+    path: String,
+    backend: Option<SecretBackendConfig>,
+    session: tauri::State<'_, VaultSessionStore>,
+    broker: tauri::State<'_, CredentialBrokerStore>,
+) -> Result<bool, String> {
+    log::info!("Unlocking vault at: {}", path);
 
-    let output = Command::new(&python_exe)
-        .arg("-c")
-        .arg(format!(
-            r#"
-import sys
-sys.path.insert(0, '{}')
-from skuldbot.libs.local_vault import LocalVault
+    let mut backend_instance = resolve_backend(&path, backend.clone());
+    backend_instance.unlock(&password)?;
 
-vault = LocalVault('{}')
-vault.unlock('{}')
-print('OK')
-"#,
-            engine_path.display(),
-            path.replace("'", "\\'"),
-            password.replace("'", "\\'")
-        ))
-        .output()
-        .map_err(|e| format!("Failed to execute Python: {}", e))?;
+    session.unlock(&path, &password, backend.clone());
+    session.unlock(&connections_session_key(), &password, None);
+    broker.start(&path, &password, backend, get_engine_path(), get_python_executable())?;
 
-    if output.status.success() {
-        println!("Vault unlocked successfully");
-        Ok(true)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to unlock vault: {}", error))
-    }
+    log::info!("Vault unlocked successfully");
+    Ok(true)
 }
 
 #[tauri::command]
-async fn vault_lock(path: String) -> Result<bool, String> {
-    // Lock is handled by not storing the password
-    // In a real implementation, we'd clear any cached state
-    println!("Vault locked: {}", path);
+async fn vault_lock(
+    path: String,
+    session: tauri::State<'_, VaultSessionStore>,
+    broker: tauri::State<'_, CredentialBrokerStore>,
+) -> Result<bool, String> {
+    session.lock(&path);
+    session.lock(&connections_session_key());
+    broker.stop(&path);
+    log::info!("Vault locked: {}", path);
     Ok(true)
 }
 
 #[tauri::command]
-async fn vault_list_secrets(path: String) -> Result<Vec<VaultSecret>, String> {
-    println!("Listing secrets from vault: {}", path);
-
-    let engine_path = get_engine_path();
-    let python_exe = get_python_executable();
-
-    // Get password from environment
-    let password = std::env::var("SKULDBOT_VAULT_PASSWORD")
-        .map_err(|_| "SKULDBOT_VAULT_PASSWORD not set. Set it in your environment to use the vault.".to_string())?;
-
-    let output = Command::new(&python_exe)
-        .arg("-c")
-        .arg(format!(
-            r#"
-import sys
-import json
-sys.path.insert(0, '{}')
-from skuldbot.libs.local_vault import LocalVault
-
-vault = LocalVault('{}')
-vault.unlock('{}')
-secrets = vault.list_secrets()
-print(json.dumps(secrets))
-"#,
-            engine_path.display(),
-            path.replace("'", "\\'"),
-            password.replace("'", "\\'")
-        ))
-        .output()
-        .map_err(|e| format!("Failed to execute Python: {}", e))?;
+async fn vault_list_secrets(
+    path: String,
+    session: tauri::State<'_, VaultSessionStore>,
+) -> Result<Vec<vault_backend::SecretMeta>, String> {
+    log::info!("Listing secrets from vault: {}", path);
 
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        let secrets: Vec<VaultSecret> = serde_json::from_str(&stdout)
-            .map_err(|e| format!("Failed to parse secrets: {}", e))?;
-        Ok(secrets)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to list secrets: {}", error))
-    }
+    let (password, backend) = session.touch(&path).ok_or_else(vault_locked_err)?;
+    let mut backend = resolve_backend(&path, backend);
+    backend.unlock(&password)?;
+    backend.list_secrets()
 }
 
 #[tauri::command]
-async fn vault_get_secret(name: String, path: String) -> Result<String, String> {
-    println!("Getting secret '{}' from vault: {}", name, path);
-
-    let engine_path = get_engine_path();
-    let python_exe = get_python_executable();
-
-    let password = std::env::var("SKULDBOT_VAULT_PASSWORD")
-        .map_err(|_| "SKULDBOT_VAULT_PASSWORD not set".to_string())?;
-
-    let output = Command::new(&python_exe)
-        .arg("-c")
-        .arg(format!(
-            r#"
-import sys
-sys.path.insert(0, '{}')
-from skuldbot.libs.local_vault import LocalVault
-
-vault = LocalVault('{}')
-vault.unlock('{}')
-value = vault.get_secret('{}')
-print(value, end='')
-"#,
-            engine_path.display(),
-            path.replace("'", "\\'"),
-            password.replace("'", "\\'"),
-            name.replace("'", "\\'")
-        ))
-        .output()
-        .map_err(|e| format!("Failed to execute Python: {}", e))?;
+async fn vault_get_secret(
+    name: String,
+    path: String,
+    session: tauri::State<'_, VaultSessionStore>,
+) -> Result<String, String> {
+    log::info!("Getting secret '{}' from vault: {}", name, path);
 
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to get secret: {}", error))
-    }
+    let (password, backend) = session.touch(&path).ok_or_else(vault_locked_err)?;
+    let mut backend = resolve_backend(&path, backend);
+    backend.unlock(&password)?;
+    backend.get_secret(&name)
 }
 
 #[tauri::command]
-async fn vault_set_secret(name: String, value: String, description: Option<String>, path: String) -> Result<bool, String> {
-    println!("Setting secret '{}' in vault: {}", name, path);
+async fn vault_set_secret(
+    name: String,
+    value: String,
+    description: Option<String>,
+    path: String,
+    session: tauri::State<'_, VaultSessionStore>,
+) -> Result<bool, String> {
+    log::info!("Setting secret '{}' in vault: {}", name, path);
 
-    let engine_path = get_engine_path();
-    let python_exe = get_python_executable();
+    let (password, backend) = session.touch(&path).ok_or_else(vault_locked_err)?;
+    let mut backend = resolve_backend(&path, backend);
+    backend.unlock(&password)?;
+    backend.set_secret(&name, &value, description.as_deref())?;
 
-    let password = std::env::var("SKULDBOT_VAULT_PASSWORD")
-        .map_err(|_| "SKULDBOT_VAULT_PASSWORD not set".to_string())?;
+    log::info!("Secret '{}' saved", name);
+    Ok(true)
+}
 
-    let desc_arg = description.map(|d| format!("description='{}'", d.replace("'", "\\'"))).unwrap_or_default();
+#[tauri::command]
+async fn vault_delete_secret(
+    name: String,
+    path: String,
+    session: tauri::State<'_, VaultSessionStore>,
+) -> Result<bool, String> {
+    log::info!("Deleting secret '{}' from vault: {}", name, path);
 
-    let output = Command::new(&python_exe)
-        .arg("-c")
-        .arg(format!(
-            r#"
-import sys
-sys.path.insert(0, '{}')
-from skuldbot.libs.local_vault import LocalVault
+    let (password, backend) = session.touch(&path).ok_or_else(vault_locked_err)?;
+    let mut backend = resolve_backend(&path, backend);
+    backend.unlock(&password)?;
+    backend.delete_secret(&name)?;
 
-vault = LocalVault('{}')
-vault.unlock('{}')
-vault.set_secret('{}', '{}'{})
-print('OK')
-"#,
-            engine_path.display(),
-            path.replace("'", "\\'"),
-            password.replace("'", "\\'"),
-            name.replace("'", "\\'"),
-            value.replace("'", "\\'"),
-            if desc_arg.is_empty() { "".to_string() } else { format!(", {}", desc_arg) }
-        ))
-        .output()
-        .map_err(|e| format!("Failed to execute Python: {}", e))?;
-
-    if output.status.success() {
-        println!("Secret '{}' saved", name);
-        Ok(true)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to set secret: {}", error))
-    }
+    log::info!("Secret '{}' deleted", name);
+    Ok(true)
 }
 
 #[tauri::command]
-async fn vault_delete_secret(name: String, path: String) -> Result<bool, String> {
-    println!("Deleting secret '{}' from vault: {}", name, path);
+async fn vault_change_password(
+    old_password: String,
+    new_password: String,
+    path: String,
+    session: tauri::State<'_, VaultSessionStore>,
+    broker: tauri::State<'_, CredentialBrokerStore>,
+) -> Result<bool, String> {
+    log::info!("Changing vault password: {}", path);
+
+    let (_, backend) = session.touch(&path).ok_or_else(vault_locked_err)?;
+    let mut backend_instance = resolve_backend(&path, backend.clone());
+    backend_instance.unlock(&old_password)?;
+    backend_instance.change_password(&old_password, &new_password)?;
+
+    // Re-open the session and broker under the new password instead of
+    // leaving them locked right after a successful rotation.
+    session.unlock(&path, &new_password, backend.clone());
+    session.unlock(&connections_session_key(), &new_password, None);
+    broker.start(&path, &new_password, backend, get_engine_path(), get_python_executable())?;
+
+    log::info!("Vault password changed");
+    Ok(true)
+}
 
-    let engine_path = get_engine_path();
-    let python_exe = get_python_executable();
+#[derive(Debug, Clone, Serialize)]
+struct VaultRotationProgress {
+    path: String,
+    stage: String,
+    completed: usize,
+    total: usize,
+}
 
-    let password = std::env::var("SKULDBOT_VAULT_PASSWORD")
-        .map_err(|_| "SKULDBOT_VAULT_PASSWORD not set".to_string())?;
+#[derive(Debug, Clone, Serialize)]
+struct VaultRotationReport {
+    secrets_rotated: usize,
+    connections_rotated: bool,
+}
 
-    let output = Command::new(&python_exe)
-        .arg("-c")
-        .arg(format!(
-            r#"
-import sys
-sys.path.insert(0, '{}')
-from skuldbot.libs.local_vault import LocalVault
+fn emit_rotation_progress(app: &tauri::AppHandle, path: &str, stage: &str, completed: usize, total: usize) {
+    let _ = app.emit(
+        "vault://rotate-progress",
+        VaultRotationProgress {
+            path: path.to_string(),
+            stage: stage.to_string(),
+            completed,
+            total,
+        },
+    );
+}
 
-vault = LocalVault('{}')
-vault.unlock('{}')
-vault.delete_secret('{}')
-print('OK')
-"#,
-            engine_path.display(),
-            path.replace("'", "\\'"),
-            password.replace("'", "\\'"),
-            name.replace("'", "\\'")
-        ))
-        .output()
-        .map_err(|e| format!("Failed to execute Python: {}", e))?;
+/// Re-encrypt every secret and the connections store under `new_password`,
+/// assuming the backend's own password has already been rotated to it.
+/// Stops at the first failure so the caller can roll the password back
+/// instead of leaving some secrets under the old key and some under the new.
+fn reencrypt_under_new_key(
+    app: &tauri::AppHandle,
+    path: &str,
+    new_backend: &mut dyn SecretBackend,
+    secrets: &[(vault_backend::SecretMeta, String)],
+    connections_plaintext: Option<&[u8]>,
+    connections_path: &PathBuf,
+    new_password: &str,
+) -> Result<usize, String> {
+    let mut completed = 0;
+    for (meta, value) in secrets {
+        new_backend
+            .set_secret(&meta.name, value, meta.description.as_deref())
+            .map_err(|e| format!("Failed to re-encrypt secret '{}': {}", meta.name, e))?;
+        completed += 1;
+        emit_rotation_progress(app, path, "re-encrypting secrets", completed, secrets.len());
+    }
 
-    if output.status.success() {
-        println!("Secret '{}' deleted", name);
-        Ok(true)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to delete secret: {}", error))
+    if let Some(plaintext) = connections_plaintext {
+        let sealed = encrypt_connections(new_password, plaintext)?;
+        fs::write(connections_path, sealed)
+            .map_err(|e| format!("Failed to write re-encrypted connections: {}", e))?;
+        emit_rotation_progress(app, path, "connections re-encrypted", secrets.len(), secrets.len());
     }
+
+    Ok(completed)
 }
 
+/// Rotate a vault's master key: decrypt every secret and the connections
+/// store with the old password, rotate the backend's own wrapped key, then
+/// re-encrypt everything under the new password. Rolls the password back
+/// and reports an error rather than leaving some secrets under the old key
+/// and some under the new.
 #[tauri::command]
-async fn vault_change_password(old_password: String, new_password: String, path: String) -> Result<bool, String> {
-    println!("Changing vault password: {}", path);
+async fn vault_rotate_key(
+    path: String,
+    old_password: String,
+    new_password: String,
+    backend: Option<SecretBackendConfig>,
+    app: tauri::AppHandle,
+    session: tauri::State<'_, VaultSessionStore>,
+    broker: tauri::State<'_, CredentialBrokerStore>,
+) -> Result<VaultRotationReport, String> {
+    log::info!("Rotating vault key: {}", path);
+
+    let mut old_backend = resolve_backend(&path, backend.clone());
+    old_backend.unlock(&old_password)?;
+
+    let secret_metas = old_backend.list_secrets()?;
+    let mut secrets = Vec::with_capacity(secret_metas.len());
+    for meta in secret_metas {
+        let value = old_backend.get_secret(&meta.name)?;
+        secrets.push((meta, value));
+    }
 
-    let engine_path = get_engine_path();
-    let python_exe = get_python_executable();
+    let connections_path = get_connections_path();
+    let connections_plaintext = if connections_path.exists() {
+        let sealed = fs::read(&connections_path).map_err(|e| format!("Failed to read connections: {}", e))?;
+        Some(decrypt_connections(&old_password, &sealed)?)
+    } else {
+        None
+    };
 
-    let output = Command::new(&python_exe)
-        .arg("-c")
-        .arg(format!(
-            r#"
-import sys
-sys.path.insert(0, '{}')
-from skuldbot.libs.local_vault import LocalVault
+    emit_rotation_progress(&app, &path, "decrypted", secrets.len(), secrets.len());
+
+    old_backend.change_password(&old_password, &new_password)?;
+
+    let mut new_backend = resolve_backend(&path, backend.clone());
+    let reencrypt_result = new_backend.unlock(&new_password).and_then(|_| {
+        reencrypt_under_new_key(
+            &app,
+            &path,
+            new_backend.as_mut(),
+            &secrets,
+            connections_plaintext.as_deref(),
+            &connections_path,
+            &new_password,
+        )
+    });
 
-vault = LocalVault('{}')
-vault.unlock('{}')
-vault.change_password('{}', '{}')
-print('OK')
-"#,
-            engine_path.display(),
-            path.replace("'", "\\'"),
-            old_password.replace("'", "\\'"),
-            old_password.replace("'", "\\'"),
-            new_password.replace("'", "\\'")
-        ))
-        .output()
-        .map_err(|e| format!("Failed to execute Python: {}", e))?;
+    let secrets_rotated = match reencrypt_result {
+        Ok(count) => count,
+        Err(e) => {
+            log::error!("❌ Vault key rotation failed, rolling back: {}", e);
+            old_backend.change_password(&new_password, &old_password)?;
+            return Err(format!("{} (rolled back to the original password)", e));
+        }
+    };
 
-    if output.status.success() {
-        println!("Vault password changed");
-        Ok(true)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to change password: {}", error))
-    }
+    session.unlock(&path, &new_password, backend.clone());
+    session.unlock(&connections_session_key(), &new_password, None);
+    broker.start(&path, &new_password, backend, get_engine_path(), get_python_executable())?;
+
+    log::info!("✅ Vault key rotated: {} secret(s) re-encrypted", secrets_rotated);
+    Ok(VaultRotationReport {
+        secrets_rotated,
+        connections_rotated: connections_plaintext.is_some(),
+    })
 }
 
 // ============================================================
@@ -1277,14 +1936,48 @@ struct LicenseValidationResult {
     #[serde(rename = "expiresAt")]
     expires_at: String,
     features: Vec<String>,
+    seats: Option<u32>,
     error: Option<String>,
 }
 
 // OpenAI API types
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct OpenAIMessage {
     role: String,
-    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<OpenAIToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "tool_call_id")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OpenAIToolCallFunction {
+    name: String,
+    arguments: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OpenAIToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    call_type: String,
+    function: OpenAIToolCallFunction,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAIToolFunction {
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAITool {
+    #[serde(rename = "type")]
+    tool_type: String,
+    function: OpenAIToolFunction,
 }
 
 #[derive(Debug, Serialize)]
@@ -1293,6 +1986,12 @@ struct OpenAIRequest {
     messages: Vec<OpenAIMessage>,
     temperature: f64,
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OpenAITool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1302,7 +2001,10 @@ struct OpenAIChoice {
 
 #[derive(Debug, Deserialize)]
 struct OpenAIResponseMessage {
-    content: String,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<OpenAIToolCall>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1311,28 +2013,439 @@ struct OpenAIResponse {
 }
 
 // Anthropic API types
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicRequest {
+    model: String,
+    messages: Vec<AnthropicMessage>,
+    max_tokens: u32,
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicContent {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(rename = "type", default)]
+    block_type: String,
 }
 
-#[derive(Debug, Serialize)]
-struct AnthropicRequest {
-    model: String,
-    messages: Vec<AnthropicMessage>,
-    max_tokens: u32,
-    system: Option<String>,
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicContent>,
+}
+
+/// How the AI planner's tool-calling loop ended: either the model finished
+/// building the plan, or asked the user something instead of guessing.
+enum PlanningOutcome {
+    Plan(Vec<AIPlanStep>),
+    ClarifyingQuestions(Vec<String>),
+}
+
+/// Upper bound on tool-call round trips before giving up — a model that
+/// never calls `finish_plan`/`ask_clarifying_question` shouldn't loop forever.
+const MAX_TOOL_LOOP_TURNS: usize = 12;
+
+fn add_plan_step_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "node_type": {"type": "string", "description": "Node type identifier, e.g. trigger.manual"},
+            "label": {"type": "string"},
+            "description": {"type": "string"},
+            "config": {"type": "object"},
+            "reasoning": {"type": "string"}
+        },
+        "required": ["node_type", "label", "description", "config"]
+    })
+}
+
+fn finish_plan_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+fn ask_clarifying_question_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": { "question": { "type": "string" } },
+        "required": ["question"]
+    })
+}
+
+fn openai_plan_tools() -> Vec<OpenAITool> {
+    vec![
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIToolFunction {
+                name: "add_plan_step".to_string(),
+                description: "Add one step to the automation plan being built.".to_string(),
+                parameters: add_plan_step_schema(),
+            },
+        },
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIToolFunction {
+                name: "finish_plan".to_string(),
+                description: "Call once every step has been added with add_plan_step; ends planning.".to_string(),
+                parameters: finish_plan_schema(),
+            },
+        },
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIToolFunction {
+                name: "ask_clarifying_question".to_string(),
+                description: "Ask the user a clarifying question instead of guessing; ends planning.".to_string(),
+                parameters: ask_clarifying_question_schema(),
+            },
+        },
+    ]
+}
+
+fn anthropic_plan_tools() -> Vec<AnthropicTool> {
+    vec![
+        AnthropicTool {
+            name: "add_plan_step".to_string(),
+            description: "Add one step to the automation plan being built.".to_string(),
+            input_schema: add_plan_step_schema(),
+        },
+        AnthropicTool {
+            name: "finish_plan".to_string(),
+            description: "Call once every step has been added with add_plan_step; ends planning.".to_string(),
+            input_schema: finish_plan_schema(),
+        },
+        AnthropicTool {
+            name: "ask_clarifying_question".to_string(),
+            description: "Ask the user a clarifying question instead of guessing; ends planning.".to_string(),
+            input_schema: ask_clarifying_question_schema(),
+        },
+    ]
+}
+
+/// Build an `AIPlanStep` from a tool call's (already-parsed) arguments,
+/// shared by the OpenAI and Anthropic tool loops.
+fn plan_step_from_tool_input(input: &serde_json::Value) -> AIPlanStep {
+    AIPlanStep {
+        id: None,
+        node_type: input.get("node_type").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        label: input.get("label").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        description: input.get("description").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        config: input.get("config").cloned().unwrap_or_else(|| serde_json::json!({})),
+        reasoning: input.get("reasoning").and_then(|v| v.as_str()).map(str::to_string),
+    }
+}
+
+/// Run the OpenAI chat-completions tool-calling loop: send the messages,
+/// read back `tool_calls`, append a synthetic result per call, and
+/// re-invoke until the model calls `finish_plan`/`ask_clarifying_question`.
+/// Returns `Ok(None)` if the model never makes a tool call at all, so the
+/// caller can fall back to the old free-text parsing path.
+async fn run_openai_tool_loop(
+    system_prompt: &str,
+    user_prompt: &str,
+    model: &str,
+    temperature: f64,
+    base_url: Option<&str>,
+    api_key: &str,
+    egress: &EgressPolicy,
+) -> Result<Option<PlanningOutcome>, String> {
+    let client = egress::build_client(egress)?;
+    let url = base_url
+        .map(|u| format!("{}/chat/completions", u.trim_end_matches('/')))
+        .unwrap_or_else(|| "https://api.openai.com/v1/chat/completions".to_string());
+    egress::check_host_allowed(&url, egress)?;
+
+    let mut messages = vec![
+        OpenAIMessage { role: "system".to_string(), content: Some(system_prompt.to_string()), tool_calls: None, tool_call_id: None },
+        OpenAIMessage { role: "user".to_string(), content: Some(user_prompt.to_string()), tool_calls: None, tool_call_id: None },
+    ];
+    let mut steps = Vec::new();
+    let mut questions = Vec::new();
+
+    for _ in 0..MAX_TOOL_LOOP_TURNS {
+        let request = OpenAIRequest {
+            model: model.to_string(),
+            messages: messages.clone(),
+            temperature,
+            max_tokens: Some(4000),
+            tools: Some(openai_plan_tools()),
+            tool_choice: Some("auto".to_string()),
+            stream: None,
+        };
+
+        let response = client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", api_key))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call OpenAI API: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("OpenAI API error ({}): {}", status, error_text));
+        }
+
+        let parsed: OpenAIResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse OpenAI response: {}", e))?;
+        let Some(choice) = parsed.choices.into_iter().next() else {
+            return Err("No response from OpenAI".to_string());
+        };
+
+        let tool_calls = match choice.message.tool_calls.clone() {
+            Some(calls) if !calls.is_empty() => calls,
+            _ => return Ok(None),
+        };
+
+        messages.push(OpenAIMessage {
+            role: "assistant".to_string(),
+            content: choice.message.content.clone(),
+            tool_calls: Some(tool_calls.clone()),
+            tool_call_id: None,
+        });
+
+        let mut finished = false;
+        for call in &tool_calls {
+            let input: serde_json::Value =
+                serde_json::from_str(&call.function.arguments).unwrap_or_else(|_| serde_json::json!({}));
+            let result_text = match call.function.name.as_str() {
+                "add_plan_step" => {
+                    steps.push(plan_step_from_tool_input(&input));
+                    format!("step {} accepted", steps.len())
+                }
+                "finish_plan" => {
+                    finished = true;
+                    "plan finished".to_string()
+                }
+                "ask_clarifying_question" => {
+                    if let Some(question) = input.get("question").and_then(|v| v.as_str()) {
+                        questions.push(question.to_string());
+                    }
+                    finished = true;
+                    "question recorded".to_string()
+                }
+                other => format!("unknown tool '{}'", other),
+            };
+
+            messages.push(OpenAIMessage {
+                role: "tool".to_string(),
+                content: Some(result_text),
+                tool_calls: None,
+                tool_call_id: Some(call.id.clone()),
+            });
+        }
+
+        if finished {
+            return Ok(Some(if !questions.is_empty() {
+                PlanningOutcome::ClarifyingQuestions(questions)
+            } else {
+                PlanningOutcome::Plan(steps)
+            }));
+        }
+    }
+
+    Err(format!("AI planner did not finish within {} tool-calling turns", MAX_TOOL_LOOP_TURNS))
+}
+
+/// Same loop as `run_openai_tool_loop`, against Anthropic's `tools` block
+/// and `tool_use`/`tool_result` content blocks instead of OpenAI's
+/// `tool_calls`. Kept on raw `serde_json::Value` messages since the
+/// assistant's tool-use blocks need to be echoed back verbatim.
+async fn run_anthropic_tool_loop(
+    system_prompt: &str,
+    user_prompt: &str,
+    model: &str,
+    api_key: &str,
+) -> Result<Option<PlanningOutcome>, String> {
+    let client = reqwest::Client::new();
+    let tools = anthropic_plan_tools();
+    let mut messages = vec![serde_json::json!({ "role": "user", "content": user_prompt })];
+    let mut steps = Vec::new();
+    let mut questions = Vec::new();
+
+    for _ in 0..MAX_TOOL_LOOP_TURNS {
+        let body = serde_json::json!({
+            "model": model,
+            "max_tokens": 4000,
+            "system": system_prompt,
+            "messages": messages,
+            "tools": tools,
+        });
+
+        let response = client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call Anthropic API: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Anthropic API error ({}): {}", status, error_text));
+        }
+
+        let parsed: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Anthropic response: {}", e))?;
+        let content = parsed.get("content").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+        let tool_uses: Vec<&serde_json::Value> =
+            content.iter().filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use")).collect();
+
+        if tool_uses.is_empty() {
+            return Ok(None);
+        }
+
+        messages.push(serde_json::json!({ "role": "assistant", "content": content }));
+
+        let mut tool_results = Vec::new();
+        let mut finished = false;
+        for call in &tool_uses {
+            let name = call.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+            let id = call.get("id").and_then(|n| n.as_str()).unwrap_or_default().to_string();
+            let input = call.get("input").cloned().unwrap_or_else(|| serde_json::json!({}));
+
+            let result_text = match name {
+                "add_plan_step" => {
+                    steps.push(plan_step_from_tool_input(&input));
+                    format!("step {} accepted", steps.len())
+                }
+                "finish_plan" => {
+                    finished = true;
+                    "plan finished".to_string()
+                }
+                "ask_clarifying_question" => {
+                    if let Some(question) = input.get("question").and_then(|v| v.as_str()) {
+                        questions.push(question.to_string());
+                    }
+                    finished = true;
+                    "question recorded".to_string()
+                }
+                other => format!("unknown tool '{}'", other),
+            };
+
+            tool_results.push(serde_json::json!({
+                "type": "tool_result",
+                "tool_use_id": id,
+                "content": result_text,
+            }));
+        }
+
+        messages.push(serde_json::json!({ "role": "user", "content": tool_results }));
+
+        if finished {
+            return Ok(Some(if !questions.is_empty() {
+                PlanningOutcome::ClarifyingQuestions(questions)
+            } else {
+                PlanningOutcome::Plan(steps)
+            }));
+        }
+    }
+
+    Err(format!("AI planner did not finish within {} tool-calling turns", MAX_TOOL_LOOP_TURNS))
 }
 
-#[derive(Debug, Deserialize)]
-struct AnthropicContent {
-    text: String,
-}
+/// Generate a plan via the tool-calling loop, falling back to the old
+/// free-text scrape (`call_openai_api`/`call_anthropic_api` +
+/// `parse_plan_from_response`) for a provider/model that reports no tool
+/// support.
+async fn generate_plan_with_tools(
+    prompt: &str,
+    provider: &str,
+    model: &str,
+    temperature: f64,
+    base_url: Option<&str>,
+    api_key: &str,
+    egress: &EgressPolicy,
+    session: &VaultSessionStore,
+) -> Result<AIPlanResponse, String> {
+    let outcome = match provider {
+        "openai" | "local" => {
+            run_openai_tool_loop(AI_PLANNER_SYSTEM_PROMPT, prompt, model, temperature, base_url, api_key, egress).await?
+        }
+        "anthropic" => run_anthropic_tool_loop(AI_PLANNER_SYSTEM_PROMPT, prompt, model, api_key).await?,
+        other => {
+            let entry = get_model_registry(session)
+                .await
+                .into_iter()
+                .find(|e| e.provider == other)
+                .ok_or_else(|| format!("Unsupported provider: {}", other))?;
+            let response = call_generic_api(&entry, AI_PLANNER_SYSTEM_PROMPT, prompt, api_key, egress).await?;
+            Some(PlanningOutcome::Plan(parse_plan_from_response(&response)?))
+        }
+    };
 
-#[derive(Debug, Deserialize)]
-struct AnthropicResponse {
-    content: Vec<AnthropicContent>,
+    match outcome {
+        Some(PlanningOutcome::Plan(plan)) => Ok(AIPlanResponse {
+            success: true,
+            plan: Some(plan),
+            error: None,
+            clarifying_questions: None,
+        }),
+        Some(PlanningOutcome::ClarifyingQuestions(questions)) => Ok(AIPlanResponse {
+            success: true,
+            plan: None,
+            error: None,
+            clarifying_questions: Some(questions),
+        }),
+        None => {
+            log::warn!("⚠️  {} returned no tool calls, falling back to text parsing", provider);
+            let response = match provider {
+                "openai" | "local" => {
+                    call_openai_api(prompt, AI_PLANNER_SYSTEM_PROMPT, model, temperature, base_url, api_key, egress).await?
+                }
+                "anthropic" => call_anthropic_api(prompt, AI_PLANNER_SYSTEM_PROMPT, model, api_key).await?,
+                _ => return Err(format!("Unsupported provider: {}", provider)),
+            };
+            let plan = match parse_plan_from_response(&response) {
+                Ok(plan) => plan,
+                Err(e) => {
+                    log::warn!("⚠️  Response didn't parse as JSON even after repair, asking the model to fix it up: {}", e);
+                    let fixup_prompt = format!(
+                        "Your previous response could not be parsed as valid JSON:\n\n{}\n\nReturn ONLY a valid JSON array of plan steps (nodeType, label, description, config, reasoning per step), with no markdown fences or commentary.",
+                        response
+                    );
+                    let fixed = match provider {
+                        "openai" | "local" => {
+                            call_openai_api(&fixup_prompt, AI_PLANNER_SYSTEM_PROMPT, model, temperature, base_url, api_key, egress).await?
+                        }
+                        "anthropic" => call_anthropic_api(&fixup_prompt, AI_PLANNER_SYSTEM_PROMPT, model, api_key).await?,
+                        _ => return Err(format!("Unsupported provider: {}", provider)),
+                    };
+                    parse_plan_from_response(&fixed)?
+                }
+            };
+            Ok(AIPlanResponse {
+                success: true,
+                plan: Some(plan),
+                error: None,
+                clarifying_questions: None,
+            })
+        }
+    }
 }
 
 const AI_PLANNER_SYSTEM_PROMPT: &str = r#"You are an expert RPA architect for SkuldBot Studio.
@@ -1424,12 +2537,19 @@ RULES:
 5. Return ONLY the JSON array, no markdown, no explanation
 6. Each step should connect logically to the next"#;
 
-fn get_api_key_from_env(provider: &str) -> Option<String> {
-    match provider {
-        "openai" => std::env::var("OPENAI_API_KEY").ok(),
-        "anthropic" => std::env::var("ANTHROPIC_API_KEY").ok(),
-        _ => None,
-    }
+/// Look up a saved connection's API key for `provider` from the decrypted
+/// connections store, used as the fallback when a command isn't given an
+/// explicit `api_key` — credentials live only in the sealed
+/// `connections.vault`, never in the process environment.
+async fn get_api_key_from_connections(provider: &str, session: &VaultSessionStore) -> Option<String> {
+    let content = load_connections_inner(session).await.ok()?;
+    let connections: Vec<serde_json::Value> = serde_json::from_str(&content).ok()?;
+    connections.iter().find_map(|c| {
+        if c.get("provider").and_then(|p| p.as_str()) != Some(provider) {
+            return None;
+        }
+        c.get("apiKey").and_then(|k| k.as_str()).map(str::to_string)
+    })
 }
 
 // ============================================================
@@ -1438,13 +2558,85 @@ fn get_api_key_from_env(provider: &str) -> Option<String> {
 
 fn get_connections_path() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    home.join(".skuldbot").join("connections.json")
+    home.join(".skuldbot").join("connections.vault")
+}
+
+const CONNECTIONS_SALT_LEN: usize = 16;
+const CONNECTIONS_NONCE_LEN: usize = 24; // XChaCha20Poly1305 uses a 24-byte nonce
+
+/// Derive the symmetric key that seals `connections.vault`, the same way a
+/// vault secret's key is derived from its master password — Argon2id over
+/// a random per-file salt, never the password itself.
+fn derive_connections_key(password: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    use argon2::{Algorithm, Argon2, Params, Version};
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Seal `plaintext` as `[salt][nonce][ciphertext]` with XChaCha20-Poly1305,
+/// generating a fresh salt and nonce per call.
+fn encrypt_connections(password: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    use chacha20poly1305::aead::Aead;
+    use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+    use rand::RngCore;
+
+    let mut salt = [0u8; CONNECTIONS_SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = derive_connections_key(password, &salt)?;
+
+    let mut nonce_bytes = [0u8; CONNECTIONS_NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+    let cipher = XChaCha20Poly1305::new(key.as_slice().into());
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|e| format!("Failed to encrypt connections: {}", e))?;
+
+    let mut sealed = Vec::with_capacity(salt.len() + nonce_bytes.len() + ciphertext.len());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce_bytes);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Reverse of `encrypt_connections`. Fails closed on a wrong password or a
+/// corrupt/truncated file rather than returning partial plaintext.
+fn decrypt_connections(password: &str, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    use chacha20poly1305::aead::Aead;
+    use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+
+    if sealed.len() < CONNECTIONS_SALT_LEN + CONNECTIONS_NONCE_LEN {
+        return Err("Connections file is corrupt".to_string());
+    }
+    let (salt, rest) = sealed.split_at(CONNECTIONS_SALT_LEN);
+    let (nonce_bytes, ciphertext) = rest.split_at(CONNECTIONS_NONCE_LEN);
+
+    let key = derive_connections_key(password, salt)?;
+    let cipher = XChaCha20Poly1305::new(key.as_slice().into());
+    cipher
+        .decrypt(XNonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| "Failed to decrypt connections: wrong vault password?".to_string())
+}
+
+/// Session key the connections store is cached under in `VaultSessionStore`.
+/// Connections aren't a project vault themselves, but they're sealed with
+/// the same master password, so `vault_unlock`/`vault_lock` mirror the
+/// project session into this key and these commands just `touch` it.
+fn connections_session_key() -> String {
+    get_connections_path().to_string_lossy().into_owned()
 }
 
 #[tauri::command]
-async fn save_connections(connections_json: String) -> Result<bool, String> {
-    println!("💾 Saving LLM connections...");
+async fn save_connections(
+    connections_json: String,
+    session: tauri::State<'_, VaultSessionStore>,
+) -> Result<bool, String> {
+    log::info!("💾 Saving LLM connections...");
 
+    let (password, _) = session.touch(&connections_session_key()).ok_or_else(vault_locked_err)?;
     let connections_path = get_connections_path();
 
     // Create directory if it doesn't exist
@@ -1452,48 +2644,60 @@ async fn save_connections(connections_json: String) -> Result<bool, String> {
         fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
     }
 
-    // TODO: In production, encrypt the JSON before storing
-    // For now, store as-is (the connections contain API keys)
-    fs::write(&connections_path, &connections_json)
+    let sealed = encrypt_connections(&password, connections_json.as_bytes())?;
+    fs::write(&connections_path, sealed)
         .map_err(|e| format!("Failed to save connections: {}", e))?;
 
-    println!("✅ Connections saved to: {}", connections_path.display());
+    log::info!("✅ Connections saved to: {}", connections_path.display());
     Ok(true)
 }
 
-#[tauri::command]
-async fn load_connections() -> Result<String, String> {
-    println!("📂 Loading LLM connections...");
+/// Shared body of the `load_connections` command, also used by callers that
+/// only have a `&VaultSessionStore` rather than a Tauri-injected `State`
+/// (`get_api_key_from_connections`, `get_model_registry`).
+async fn load_connections_inner(session: &VaultSessionStore) -> Result<String, String> {
+    log::info!("📂 Loading LLM connections...");
 
     let connections_path = get_connections_path();
 
     if !connections_path.exists() {
-        println!("ℹ️  No connections file found");
+        log::info!("ℹ️  No connections file found");
         return Ok("[]".to_string());
     }
 
-    let content = fs::read_to_string(&connections_path)
-        .map_err(|e| format!("Failed to read connections: {}", e))?;
+    let (password, _) = session.touch(&connections_session_key()).ok_or_else(vault_locked_err)?;
+    let sealed = fs::read(&connections_path).map_err(|e| format!("Failed to read connections: {}", e))?;
+    let plaintext = decrypt_connections(&password, &sealed)?;
+    let content = String::from_utf8(plaintext)
+        .map_err(|e| format!("Connections file is corrupt: {}", e))?;
 
-    println!("✅ Loaded connections from: {}", connections_path.display());
+    log::info!("✅ Loaded connections from: {}", connections_path.display());
     Ok(content)
 }
 
+#[tauri::command]
+async fn load_connections(session: tauri::State<'_, VaultSessionStore>) -> Result<String, String> {
+    load_connections_inner(session.inner()).await
+}
+
 #[tauri::command]
 async fn test_llm_connection(
     provider: String,
     api_key: String,
     base_url: Option<String>,
+    egress: Option<EgressPolicy>,
 ) -> Result<serde_json::Value, String> {
-    println!("🔌 Testing {} connection...", provider);
+    log::info!("🔌 Testing {} connection...", provider);
 
-    let client = reqwest::Client::new();
+    let egress = egress.unwrap_or_default();
+    let client = egress::build_client(&egress)?;
 
     match provider.as_str() {
         "openai" | "local" => {
             let url = base_url
                 .map(|u| format!("{}/models", u.trim_end_matches('/')))
                 .unwrap_or_else(|| "https://api.openai.com/v1/models".to_string());
+            egress::check_host_allowed(&url, &egress)?;
 
             let response = client
                 .get(&url)
@@ -1503,7 +2707,7 @@ async fn test_llm_connection(
                 .map_err(|e| format!("Connection failed: {}", e))?;
 
             if response.status().is_success() {
-                println!("✅ Connection successful!");
+                log::info!("✅ Connection successful!");
                 Ok(serde_json::json!({
                     "success": true,
                     "message": "Connection successful! API key is valid."
@@ -1511,7 +2715,7 @@ async fn test_llm_connection(
             } else {
                 let status = response.status();
                 let error_text = response.text().await.unwrap_or_default();
-                println!("❌ Connection failed: {} - {}", status, error_text);
+                log::error!("❌ Connection failed: {} - {}", status, error_text);
                 Ok(serde_json::json!({
                     "success": false,
                     "message": format!("Authentication failed ({}). Please check your API key.", status.as_u16())
@@ -1535,7 +2739,7 @@ async fn test_llm_connection(
                 .map_err(|e| format!("Connection failed: {}", e))?;
 
             if response.status().is_success() {
-                println!("✅ Anthropic connection successful!");
+                log::info!("✅ Anthropic connection successful!");
                 Ok(serde_json::json!({
                     "success": true,
                     "message": "Connection successful! API key is valid."
@@ -1543,7 +2747,7 @@ async fn test_llm_connection(
             } else {
                 let status = response.status();
                 let error_text = response.text().await.unwrap_or_default();
-                println!("❌ Anthropic connection failed: {} - {}", status, error_text);
+                log::error!("❌ Anthropic connection failed: {} - {}", status, error_text);
                 Ok(serde_json::json!({
                     "success": false,
                     "message": format!("Authentication failed ({}). Please check your API key.", status.as_u16())
@@ -1564,27 +2768,36 @@ async fn call_openai_api(
     temperature: f64,
     base_url: Option<&str>,
     api_key: &str,
+    egress: &EgressPolicy,
 ) -> Result<String, String> {
-    let client = reqwest::Client::new();
+    let client = egress::build_client(egress)?;
 
     let url = base_url
         .map(|u| format!("{}/chat/completions", u.trim_end_matches('/')))
         .unwrap_or_else(|| "https://api.openai.com/v1/chat/completions".to_string());
+    egress::check_host_allowed(&url, egress)?;
 
     let request = OpenAIRequest {
         model: model.to_string(),
         messages: vec![
             OpenAIMessage {
                 role: "system".to_string(),
-                content: system_prompt.to_string(),
+                content: Some(system_prompt.to_string()),
+                tool_calls: None,
+                tool_call_id: None,
             },
             OpenAIMessage {
                 role: "user".to_string(),
-                content: prompt.to_string(),
+                content: Some(prompt.to_string()),
+                tool_calls: None,
+                tool_call_id: None,
             },
         ],
         temperature,
         max_tokens: Some(4000),
+        tools: None,
+        tool_choice: None,
+        stream: None,
     };
 
     let response = client
@@ -1610,7 +2823,7 @@ async fn call_openai_api(
     openai_response
         .choices
         .first()
-        .map(|c| c.message.content.clone())
+        .and_then(|c| c.message.content.clone())
         .ok_or_else(|| "No response from OpenAI".to_string())
 }
 
@@ -1626,10 +2839,12 @@ async fn call_anthropic_api(
         model: model.to_string(),
         messages: vec![AnthropicMessage {
             role: "user".to_string(),
-            content: prompt.to_string(),
+            content: serde_json::json!(prompt),
         }],
         max_tokens: 4000,
         system: Some(system_prompt.to_string()),
+        tools: None,
+        stream: None,
     };
 
     let response = client
@@ -1655,46 +2870,529 @@ async fn call_anthropic_api(
 
     anthropic_response
         .content
-        .first()
-        .map(|c| c.text.clone())
+        .iter()
+        .find(|c| c.block_type == "text")
+        .and_then(|c| c.text.clone())
         .ok_or_else(|| "No response from Anthropic".to_string())
 }
 
-fn parse_plan_from_response(response: &str) -> Result<Vec<AIPlanStep>, String> {
-    // Try to extract JSON from the response
-    let json_str = if response.contains('[') {
-        // Find the JSON array in the response
+/// How a generic provider's API key is attached to the request. Covers the
+/// two shapes most OpenAI-compatible and vendor-specific APIs use; anything
+/// else (query-string keys, mTLS) is out of scope for a registry row.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+enum AuthHeaderStyle {
+    #[default]
+    Bearer,
+    ApiKey,
+    None,
+}
+
+/// A provider added from Settings without a code change: the raw request
+/// body and the path to the completion text in the response both come from
+/// the row itself, so Gemini/Mistral/Groq/Ollama/any OpenAI-compatible
+/// endpoint only needs a connections-store entry, not a new `match` arm.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GenericModelEntry {
+    provider: String,
+    #[serde(rename = "displayName")]
+    display_name: String,
+    #[serde(rename = "endpointUrl")]
+    endpoint_url: String,
+    #[serde(rename = "authHeaderStyle", default)]
+    auth_header_style: AuthHeaderStyle,
+    #[serde(rename = "maxTokens")]
+    max_tokens: Option<u32>,
+    #[serde(rename = "bodyTemplate")]
+    body_template: serde_json::Value,
+    #[serde(rename = "extractPath")]
+    extract_path: String,
+}
+
+/// Substitute `{{system}}`/`{{user}}` placeholders anywhere they appear
+/// inside a registry entry's `bodyTemplate`, recursing into nested
+/// objects/arrays so `max_tokens`, `model`, and vendor-specific fields stay
+/// exactly as the template author wrote them.
+fn fill_body_template(template: &serde_json::Value, system_prompt: &str, user_prompt: &str) -> serde_json::Value {
+    match template {
+        serde_json::Value::String(s) => serde_json::Value::String(
+            s.replace("{{system}}", system_prompt).replace("{{user}}", user_prompt),
+        ),
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items.iter().map(|v| fill_body_template(v, system_prompt, user_prompt)).collect(),
+        ),
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), fill_body_template(v, system_prompt, user_prompt)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Walk a JSONPath-style dotted path (`choices.0.message.content`,
+/// `content.0.text`) to pull the completion text out of whatever response
+/// shape a registry entry's `extractPath` describes.
+fn extract_by_path(value: &serde_json::Value, path: &str) -> Option<String> {
+    let mut current = value;
+    for segment in path.split('.') {
+        current = match segment.parse::<usize>() {
+            Ok(index) => current.get(index)?,
+            Err(_) => current.get(segment)?,
+        };
+    }
+    current.as_str().map(str::to_string)
+}
+
+/// Load every model-registry row out of the connections store. Registry
+/// entries ride in the same encrypted file as saved provider connections,
+/// tagged `entryType: "model"` so they aren't mistaken for a regular
+/// provider/apiKey row.
+async fn get_model_registry(session: &VaultSessionStore) -> Vec<GenericModelEntry> {
+    let Ok(content) = load_connections_inner(session).await else {
+        return Vec::new();
+    };
+    let Ok(entries) = serde_json::from_str::<Vec<serde_json::Value>>(&content) else {
+        return Vec::new();
+    };
+    entries
+        .into_iter()
+        .filter(|e| e.get("entryType").and_then(|t| t.as_str()) == Some("model"))
+        .filter_map(|e| serde_json::from_value(e).ok())
+        .collect()
+}
+
+/// Call a registry-backed provider: fill its body template, send it exactly
+/// as written, and pull the completion text out at `extract_path`.
+async fn call_generic_api(
+    entry: &GenericModelEntry,
+    system_prompt: &str,
+    user_prompt: &str,
+    api_key: &str,
+    egress: &EgressPolicy,
+) -> Result<String, String> {
+    let client = egress::build_client(egress)?;
+    egress::check_host_allowed(&entry.endpoint_url, egress)?;
+
+    let body = fill_body_template(&entry.body_template, system_prompt, user_prompt);
+    let mut request = client.post(&entry.endpoint_url).header("Content-Type", "application/json");
+    request = match entry.auth_header_style {
+        AuthHeaderStyle::Bearer => request.header("Authorization", format!("Bearer {}", api_key)),
+        AuthHeaderStyle::ApiKey => request.header("x-api-key", api_key),
+        AuthHeaderStyle::None => request,
+    };
+
+    let response = request
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call {}: {}", entry.display_name, e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("{} error ({}): {}", entry.display_name, status, error_text));
+    }
+
+    let value: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {} response: {}", entry.display_name, e))?;
+
+    extract_by_path(&value, &entry.extract_path).ok_or_else(|| {
+        format!(
+            "No completion found at '{}' in {} response",
+            entry.extract_path, entry.display_name
+        )
+    })
+}
+
+#[tauri::command]
+async fn ai_list_model_registry(
+    license: tauri::State<'_, LicenseState>,
+    session: tauri::State<'_, VaultSessionStore>,
+) -> Result<Vec<GenericModelEntry>, String> {
+    capability::require_feature(&license, "ai_list_model_registry")?;
+    Ok(get_model_registry(session.inner()).await)
+}
+
+/// Find the JSON array inside a completion, bounding it to the first `[`
+/// and the last `]` if one is present, for the common case where the model
+/// wraps it in prose or a code fence.
+fn extract_json_array(response: &str) -> String {
+    if response.contains('[') {
         let start = response.find('[').unwrap_or(0);
         let end = response.rfind(']').map(|i| i + 1).unwrap_or(response.len());
-        &response[start..end]
+        response[start..end].to_string()
     } else {
-        response
+        response.to_string()
+    }
+}
+
+/// Strip a ```json / ``` markdown code fence wrapped around the payload.
+/// A no-op when there's no fence, so it's safe to always run.
+fn strip_code_fences(text: &str) -> String {
+    let trimmed = text.trim();
+    let Some(start) = trimmed.find("```") else {
+        return trimmed.to_string();
+    };
+    let after_marker = &trimmed[start + 3..];
+    // Skip an optional language tag on the opening fence line (```json).
+    let body_start = after_marker.find('\n').map(|i| i + 1).unwrap_or(0);
+    let body = &after_marker[body_start..];
+    match body.rfind("```") {
+        Some(end) => body[..end].trim().to_string(),
+        None => body.trim().to_string(),
+    }
+}
+
+/// Remove a comma that's immediately followed (modulo whitespace) by a
+/// closing bracket or brace, ignoring commas inside strings.
+fn remove_trailing_commas(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    while let Some(ch) = chars.next() {
+        if in_string {
+            result.push(ch);
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => {
+                in_string = true;
+                result.push(ch);
+            }
+            ',' => {
+                let mut lookahead = chars.clone();
+                let closes = loop {
+                    match lookahead.peek() {
+                        Some(c) if c.is_whitespace() => {
+                            lookahead.next();
+                        }
+                        Some(']') | Some('}') => break true,
+                        _ => break false,
+                    }
+                };
+                if !closes {
+                    result.push(ch);
+                }
+            }
+            _ => result.push(ch),
+        }
+    }
+
+    result
+}
+
+/// Track bracket/brace/string nesting across `text`, returning the byte
+/// index just past the end of the last top-level array element that
+/// closed cleanly (depth back down to 1, i.e. still inside the outer
+/// array), plus whether the text as a whole is already balanced.
+fn scan_nesting(text: &str) -> (Option<usize>, bool) {
+    let mut stack: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut last_safe_end = None;
+
+    for (i, ch) in text.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '[' | '{' => stack.push(ch),
+            ']' | '}' => {
+                stack.pop();
+                if stack.len() <= 1 {
+                    last_safe_end = Some(i + ch.len_utf8());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (last_safe_end, !in_string && stack.is_empty())
+}
+
+/// Drop a dangling, unterminated final element left by a response cut off
+/// mid-object, then close whatever brackets/braces are still open at that
+/// point. Returns `text` unchanged if it's already balanced, or if nothing
+/// ever closed cleanly (nothing safe to fall back to).
+fn balance_and_truncate(text: &str) -> String {
+    let (last_safe_end, already_balanced) = scan_nesting(text);
+    if already_balanced {
+        return text.to_string();
+    }
+    let Some(end) = last_safe_end else {
+        return text.to_string();
     };
 
-    serde_json::from_str(json_str)
+    let mut truncated = text[..end].to_string();
+    // Replay nesting up to the truncation point to know what's still open.
+    let mut still_open: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for ch in truncated.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '[' | '{' => still_open.push(ch),
+            ']' | '}' => {
+                still_open.pop();
+            }
+            _ => {}
+        }
+    }
+    for open in still_open.iter().rev() {
+        truncated.push(if *open == '[' { ']' } else { '}' });
+    }
+    truncated
+}
+
+/// Repair the common ways a model's completion fails strict JSON parsing:
+/// a markdown fence wrapped around the payload, a trailing comma before a
+/// closing bracket, and a response truncated mid-element. Runs as a second
+/// pass only after a strict parse has already failed.
+fn repair_json(text: &str) -> String {
+    let stripped = strip_code_fences(text);
+    let no_trailing_commas = remove_trailing_commas(&stripped);
+    balance_and_truncate(&no_trailing_commas)
+}
+
+fn parse_plan_from_response(response: &str) -> Result<Vec<AIPlanStep>, String> {
+    let json_str = extract_json_array(response);
+
+    if let Ok(steps) = serde_json::from_str(&json_str) {
+        return Ok(steps);
+    }
+
+    let repaired = repair_json(&json_str);
+    serde_json::from_str(&repaired)
         .map_err(|e| format!("Failed to parse LLM response as JSON: {}. Response: {}", e, json_str))
 }
 
+#[cfg(test)]
+mod plan_json_repair_tests {
+    use super::*;
+
+    #[test]
+    fn parses_well_formed_response_unchanged() {
+        let response = r#"[{"nodeType":"trigger.manual","label":"Start","description":"d","config":{},"reasoning":null}]"#;
+        let steps = parse_plan_from_response(response).expect("well-formed JSON should parse");
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].node_type, "trigger.manual");
+    }
+
+    #[test]
+    fn strips_markdown_code_fence() {
+        let response = "Here is your plan:\n```json\n[{\"nodeType\":\"trigger.manual\",\"label\":\"Start\",\"description\":\"d\",\"config\":{},\"reasoning\":null}]\n```";
+        let steps = parse_plan_from_response(response).expect("fenced JSON should repair");
+        assert_eq!(steps.len(), 1);
+    }
+
+    #[test]
+    fn removes_trailing_comma_before_closing_bracket() {
+        let response = r#"[{"nodeType":"trigger.manual","label":"Start","description":"d","config":{},"reasoning":null},]"#;
+        let steps = parse_plan_from_response(response).expect("trailing comma should be repaired");
+        assert_eq!(steps.len(), 1);
+    }
+
+    #[test]
+    fn recovers_from_truncated_final_element() {
+        let response = r#"[{"nodeType":"trigger.manual","label":"Start","description":"d","config":{},"reasoning":null},{"nodeType":"logging.log","label":"Log","description":"partial","config":{"message":"cut off"#;
+        let steps = parse_plan_from_response(response).expect("truncated trailing element should be dropped");
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].node_type, "trigger.manual");
+    }
+
+    #[test]
+    fn recovers_from_fence_plus_trailing_comma_plus_truncation() {
+        let response = "```json\n[{\"nodeType\":\"trigger.manual\",\"label\":\"Start\",\"description\":\"d\",\"config\":{},\"reasoning\":null},{\"nodeType\":\"broken";
+        let steps = parse_plan_from_response(response).expect("combined corruption should still repair");
+        assert_eq!(steps.len(), 1);
+    }
+
+    #[test]
+    fn gives_up_cleanly_when_nothing_ever_closed() {
+        let response = r#"[{"nodeType":"trigger.manual","label":"Start"#;
+        assert!(parse_plan_from_response(response).is_err());
+    }
+}
+
+/// Streaming counterpart to `generate_plan_with_tools`: emits each completed
+/// step to the frontend as soon as it arrives instead of waiting for the
+/// whole completion, then emits a final completion/error event. Runs the
+/// plain free-text completion path rather than the tool-calling loop, since
+/// providers' SSE protocols interleave tool-call deltas in a shape this
+/// step-level parser doesn't attempt to track.
+async fn generate_plan_streaming(
+    app: &tauri::AppHandle,
+    prompt: &str,
+    provider: &str,
+    model: &str,
+    temperature: f64,
+    base_url: Option<&str>,
+    api_key: &str,
+    egress: &EgressPolicy,
+) -> Result<AIPlanResponse, String> {
+    let on_step = |raw: String| {
+        if let Ok(step) = serde_json::from_str::<AIPlanStep>(&raw) {
+            let _ = app.emit("ai://plan-step", &step);
+        }
+    };
+
+    // A failure before the full text arrives (client setup, a blocked host,
+    // a broken stream) also ends with an `ai://plan-error` event.
+    let streamed = async {
+    let client = egress::build_client(egress)?;
+    Ok::<String, String>(match provider {
+        "openai" | "local" => {
+            let url = base_url
+                .map(|u| format!("{}/chat/completions", u.trim_end_matches('/')))
+                .unwrap_or_else(|| "https://api.openai.com/v1/chat/completions".to_string());
+            egress::check_host_allowed(&url, egress)?;
+
+            let request = OpenAIRequest {
+                model: model.to_string(),
+                messages: vec![
+                    OpenAIMessage {
+                        role: "system".to_string(),
+                        content: Some(AI_PLANNER_SYSTEM_PROMPT.to_string()),
+                        tool_calls: None,
+                        tool_call_id: None,
+                    },
+                    OpenAIMessage {
+                        role: "user".to_string(),
+                        content: Some(prompt.to_string()),
+                        tool_calls: None,
+                        tool_call_id: None,
+                    },
+                ],
+                temperature,
+                max_tokens: Some(4000),
+                tools: None,
+                tool_choice: None,
+                stream: Some(true),
+            };
+            let body = serde_json::to_value(&request)
+                .map_err(|e| format!("Failed to build request body: {}", e))?;
+            ai_streaming::stream_openai_completion(&client, &url, api_key, &body, on_step).await?
+        }
+        "anthropic" => {
+            let request = AnthropicRequest {
+                model: model.to_string(),
+                messages: vec![AnthropicMessage {
+                    role: "user".to_string(),
+                    content: serde_json::json!(prompt),
+                }],
+                max_tokens: 4000,
+                system: Some(AI_PLANNER_SYSTEM_PROMPT.to_string()),
+                tools: None,
+                stream: Some(true),
+            };
+            let body = serde_json::to_value(&request)
+                .map_err(|e| format!("Failed to build request body: {}", e))?;
+            ai_streaming::stream_anthropic_completion(&client, api_key, &body, on_step).await?
+        }
+        other => {
+            // Registry-backed providers send an opaque body template with
+            // no agreed-upon streaming shape, so this falls back to a
+            // single blocking call; the per-step events still fire once
+            // the final plan is parsed below.
+            let entry = get_model_registry(app.state::<VaultSessionStore>().inner())
+                .await
+                .into_iter()
+                .find(|e| e.provider == other)
+                .ok_or_else(|| format!("Unsupported provider for streaming: {}", other))?;
+            call_generic_api(&entry, AI_PLANNER_SYSTEM_PROMPT, prompt, api_key, egress).await?
+        }
+    })
+    }
+    .await;
+    let full_text = match streamed {
+        Ok(text) => text,
+        Err(e) => {
+            let _ = app.emit("ai://plan-error", &e);
+            return Err(e);
+        }
+    };
+
+    match parse_plan_from_response(&full_text) {
+        Ok(plan) => {
+            if provider != "openai" && provider != "local" && provider != "anthropic" {
+                for step in &plan {
+                    let _ = app.emit("ai://plan-step", step);
+                }
+            }
+            let response = AIPlanResponse {
+                success: true,
+                plan: Some(plan),
+                error: None,
+                clarifying_questions: None,
+            };
+            let _ = app.emit("ai://plan-complete", &response);
+            Ok(response)
+        }
+        Err(e) => {
+            let _ = app.emit("ai://plan-error", &e);
+            Err(e)
+        }
+    }
+}
+
 #[tauri::command]
 async fn ai_generate_plan(
+    app: tauri::AppHandle,
     description: String,
     provider: String,
     model: String,
     temperature: f64,
     base_url: Option<String>,
     api_key: Option<String>,
+    egress: Option<EgressPolicy>,
+    stream: Option<bool>,
+    license: tauri::State<'_, LicenseState>,
+    session: tauri::State<'_, VaultSessionStore>,
 ) -> Result<AIPlanResponse, String> {
-    println!("🤖 AI Generating plan for: {}", description);
-    println!("   Provider: {}, Model: {}", provider, model);
+    capability::require_feature(&license, "ai_generate_plan")?;
+    let egress = egress.unwrap_or_default();
+    log::info!("🤖 AI Generating plan for: {}", description);
+    log::info!("   Provider: {}, Model: {}", provider, model);
 
     // Get API key from parameter or fall back to environment
     let api_key = match api_key.filter(|k| !k.is_empty()) {
         Some(key) => key,
-        None => match get_api_key_from_env(&provider) {
+        None => match get_api_key_from_connections(&provider, session.inner()).await {
             Some(key) => key,
             None => {
                 // Return mock response if no API key
-                println!("⚠️  No API key found for {}, using mock response", provider);
+                log::warn!("⚠️  No API key found for {}, using mock response", provider);
                 let mock_plan = vec![
                     AIPlanStep {
                         id: None,
@@ -1731,50 +3429,31 @@ async fn ai_generate_plan(
         description
     );
 
-    let result = match provider.as_str() {
-        "openai" | "local" => {
-            call_openai_api(
-                &prompt,
-                AI_PLANNER_SYSTEM_PROMPT,
-                &model,
-                temperature,
-                base_url.as_deref(),
-                &api_key,
-            )
-            .await
-        }
-        "anthropic" => {
-            call_anthropic_api(&prompt, AI_PLANNER_SYSTEM_PROMPT, &model, &api_key).await
-        }
-        _ => Err(format!("Unsupported provider: {}", provider)),
-    };
-
-    match result {
-        Ok(response) => {
-            println!("📝 LLM Response received ({} chars)", response.len());
-            match parse_plan_from_response(&response) {
-                Ok(plan) => {
-                    println!("✅ Parsed {} steps from LLM response", plan.len());
-                    Ok(AIPlanResponse {
-                        success: true,
-                        plan: Some(plan),
-                        error: None,
-                        clarifying_questions: None,
-                    })
-                }
-                Err(e) => {
-                    println!("❌ Failed to parse LLM response: {}", e);
-                    Ok(AIPlanResponse {
-                        success: false,
-                        plan: None,
-                        error: Some(e),
-                        clarifying_questions: None,
-                    })
-                }
+    if stream.unwrap_or(false) {
+        return match generate_plan_streaming(&app, &prompt, &provider, &model, temperature, base_url.as_deref(), &api_key, &egress).await {
+            Ok(plan_response) => {
+                log::info!("✅ AI plan generated (streamed)");
+                Ok(plan_response)
+            }
+            Err(e) => {
+                log::error!("❌ Streaming LLM API call failed: {}", e);
+                Ok(AIPlanResponse {
+                    success: false,
+                    plan: None,
+                    error: Some(e),
+                    clarifying_questions: None,
+                })
             }
+        };
+    }
+
+    match generate_plan_with_tools(&prompt, &provider, &model, temperature, base_url.as_deref(), &api_key, &egress, session.inner()).await {
+        Ok(plan_response) => {
+            log::info!("✅ AI plan generated");
+            Ok(plan_response)
         }
         Err(e) => {
-            println!("❌ LLM API call failed: {}", e);
+            log::error!("❌ LLM API call failed: {}", e);
             Ok(AIPlanResponse {
                 success: false,
                 plan: None,
@@ -1787,6 +3466,7 @@ async fn ai_generate_plan(
 
 #[tauri::command]
 async fn ai_refine_plan(
+    app: tauri::AppHandle,
     current_plan: String,
     user_request: String,
     conversation_history: String,
@@ -1795,8 +3475,14 @@ async fn ai_refine_plan(
     temperature: f64,
     base_url: Option<String>,
     api_key: Option<String>,
+    egress: Option<EgressPolicy>,
+    stream: Option<bool>,
+    license: tauri::State<'_, LicenseState>,
+    session: tauri::State<'_, VaultSessionStore>,
 ) -> Result<AIPlanResponse, String> {
-    println!("🤖 AI Refining plan based on: {}", user_request);
+    capability::require_feature(&license, "ai_refine_plan")?;
+    log::info!("🤖 AI Refining plan based on: {}", user_request);
+    let egress = egress.unwrap_or_default();
 
     // Parse current plan
     let plan: Vec<AIPlanStep> = serde_json::from_str(&current_plan)
@@ -1805,11 +3491,11 @@ async fn ai_refine_plan(
     // Get API key from parameter or fall back to environment
     let api_key = match api_key.filter(|k| !k.is_empty()) {
         Some(key) => key,
-        None => match get_api_key_from_env(&provider) {
+        None => match get_api_key_from_connections(&provider, session.inner()).await {
             Some(key) => key,
             None => {
                 // Return same plan if no API key
-                println!("⚠️  No API key found for {}, returning original plan", provider);
+                log::warn!("⚠️  No API key found for {}, returning original plan", provider);
                 return Ok(AIPlanResponse {
                     success: true,
                     plan: Some(plan),
@@ -1839,46 +3525,41 @@ Follow the same format as the original plan with nodeType, label, description, c
         current_plan, user_request, conversation_history
     );
 
-    let result = match provider.as_str() {
-        "openai" | "local" => {
-            call_openai_api(
-                &refinement_prompt,
-                AI_PLANNER_SYSTEM_PROMPT,
-                &model,
-                temperature,
-                base_url.as_deref(),
-                &api_key,
-            )
-            .await
-        }
-        "anthropic" => {
-            call_anthropic_api(&refinement_prompt, AI_PLANNER_SYSTEM_PROMPT, &model, &api_key).await
-        }
-        _ => Err(format!("Unsupported provider: {}", provider)),
-    };
-
-    match result {
-        Ok(response) => {
-            match parse_plan_from_response(&response) {
-                Ok(refined_plan) => {
-                    println!("✅ Refined plan has {} steps", refined_plan.len());
-                    Ok(AIPlanResponse {
-                        success: true,
-                        plan: Some(refined_plan),
-                        error: None,
-                        clarifying_questions: None,
-                    })
-                }
-                Err(e) => {
-                    // If parsing fails, return original plan with error
-                    Ok(AIPlanResponse {
-                        success: false,
-                        plan: Some(plan),
-                        error: Some(format!("Failed to parse refined plan: {}", e)),
-                        clarifying_questions: None,
-                    })
-                }
+    if stream.unwrap_or(false) {
+        return match generate_plan_streaming(&app, &refinement_prompt, &provider, &model, temperature, base_url.as_deref(), &api_key, &egress).await {
+            Ok(plan_response) if plan_response.plan.is_some() => {
+                log::info!("✅ Refined plan has {} steps (streamed)", plan_response.plan.as_ref().unwrap().len());
+                Ok(plan_response)
             }
+            Ok(plan_response) => Ok(AIPlanResponse {
+                success: true,
+                plan: Some(plan),
+                error: None,
+                clarifying_questions: plan_response.clarifying_questions,
+            }),
+            Err(e) => Ok(AIPlanResponse {
+                success: false,
+                plan: Some(plan),
+                error: Some(e),
+                clarifying_questions: None,
+            }),
+        };
+    }
+
+    match generate_plan_with_tools(&refinement_prompt, &provider, &model, temperature, base_url.as_deref(), &api_key, &egress, session.inner()).await {
+        Ok(plan_response) if plan_response.plan.is_some() => {
+            log::info!("✅ Refined plan has {} steps", plan_response.plan.as_ref().unwrap().len());
+            Ok(plan_response)
+        }
+        Ok(plan_response) => {
+            // The model asked a clarifying question instead of refining;
+            // surface it alongside the untouched original plan.
+            Ok(AIPlanResponse {
+                success: true,
+                plan: Some(plan),
+                error: None,
+                clarifying_questions: plan_response.clarifying_questions,
+            })
         }
         Err(e) => {
             // On API error, return original plan with error
@@ -1897,60 +3578,57 @@ Follow the same format as the original plan with nodeType, label, description, c
 // ============================================================
 
 #[tauri::command]
-async fn validate_license(license_key: String) -> Result<LicenseValidationResult, String> {
-    println!("🔑 Validating license: {}...", &license_key[..8.min(license_key.len())]);
-
-    // TODO: Implement actual license validation against Orchestrator API
-    // For development, we'll validate based on key format
-
-    // Mock validation logic
-    // In production: call POST /api/licenses/validate on Orchestrator
-
-    let key_upper = license_key.to_uppercase();
-
-    // Check key format and determine module
-    let (valid, module, features) = if key_upper.starts_with("STUDIO-") {
-        (true, "studio", vec!["flowEditor", "localExecution", "projectManagement", "170+BaseNodes"])
-    } else if key_upper.starts_with("SKULDAI-") {
-        (true, "skuldai", vec!["aiPlanner", "aiRefinement", "localLLM", "ai.llm_prompt", "ai.extract_data"])
-    } else if key_upper.starts_with("COMPLY-") {
-        (true, "skuldcompliance", vec!["compliance.protect_pii", "compliance.protect_phi", "compliance.audit_log"])
-    } else if key_upper.starts_with("DATAQ-") {
-        (true, "skulddataquality", vec!["dataquality.validate", "dataquality.profile_data", "ai.repair_data"])
-    } else if key_upper.starts_with("DEMO-") {
-        // Demo key activates all modules for testing
-        (true, "studio", vec!["flowEditor", "localExecution", "projectManagement"])
-    } else {
-        (false, "", vec![])
+async fn validate_license(
+    license_key: String,
+    license_state: tauri::State<'_, LicenseState>,
+) -> Result<LicenseValidationResult, String> {
+    log::info!("🔑 Validating license: {}...", &license_key[..8.min(license_key.len())]);
+
+    let claims = match license::verify_license_token(&license_key) {
+        Ok(claims) => claims,
+        Err(e) => {
+            log::error!("❌ Invalid license key: {}", e);
+            license_state.clear();
+            return Ok(LicenseValidationResult {
+                valid: false,
+                module: String::new(),
+                expires_at: String::new(),
+                features: vec![],
+                seats: None,
+                error: Some(e),
+            });
+        }
     };
 
-    if valid {
-        // Set expiration to 1 year from now for demo
-        let expires_at = chrono::Utc::now()
-            .checked_add_signed(chrono::Duration::days(365))
-            .unwrap_or_else(chrono::Utc::now)
-            .to_rfc3339();
-
-        println!("✅ License valid for module: {}", module);
-
-        Ok(LicenseValidationResult {
-            valid: true,
-            module: module.to_string(),
-            expires_at,
-            features: features.into_iter().map(String::from).collect(),
-            error: None,
-        })
-    } else {
-        println!("❌ Invalid license key");
-
-        Ok(LicenseValidationResult {
+    if license::check_revocation_online(&license_key).await == Some(true) {
+        log::error!("❌ License has been revoked");
+        license_state.clear();
+        return Ok(LicenseValidationResult {
             valid: false,
-            module: String::new(),
+            module: claims.module,
             expires_at: String::new(),
             features: vec![],
-            error: Some("Invalid license key format".to_string()),
-        })
+            seats: None,
+            error: Some("License has been revoked".to_string()),
+        });
     }
+
+    let expires_at = DateTime::<Utc>::from_timestamp(claims.exp, 0)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default();
+
+    log::info!("✅ License valid for module: {}", claims.module);
+
+    license_state.set(claims.clone());
+
+    Ok(LicenseValidationResult {
+        valid: true,
+        module: claims.module,
+        expires_at,
+        features: claims.features,
+        seats: Some(claims.seats),
+        error: None,
+    })
 }
 
 // ============================================================
@@ -1959,7 +3637,7 @@ async fn validate_license(license_key: String) -> Result<LicenseValidationResult
 
 #[tauri::command]
 async fn read_directory(path: String) -> Result<Vec<FileInfo>, String> {
-    println!("📂 Reading directory: {}", path);
+    log::info!("📂 Reading directory: {}", path);
 
     let dir_path = PathBuf::from(&path);
     if !dir_path.exists() {
@@ -2002,7 +3680,7 @@ async fn file_exists(path: String) -> Result<bool, String> {
 
 #[tauri::command]
 async fn get_excel_sheets(file_path: String) -> Result<Vec<String>, String> {
-    println!("📊 Getting Excel sheets from: {}", file_path);
+    log::info!("📊 Getting Excel sheets from: {}", file_path);
 
     let path = PathBuf::from(&file_path);
     if !path.exists() {
@@ -2044,7 +3722,7 @@ except Exception as e:
         let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
         let sheets: Vec<String> = serde_json::from_str(&stdout)
             .unwrap_or_else(|_| vec![]);
-        println!("✅ Found {} sheets", sheets.len());
+        log::info!("✅ Found {} sheets", sheets.len());
         Ok(sheets)
     } else {
         let error = String::from_utf8_lossy(&output.stderr);
@@ -2052,16 +3730,117 @@ except Exception as e:
     }
 }
 
+/// Print CLI usage and exit with `code`.
+fn print_cli_usage_and_exit(code: i32) -> ! {
+    eprintln!("Usage:");
+    eprintln!("  skuldbot run <bot.json> [--loglevel LEVEL]");
+    eprintln!("  skuldbot validate <bot.json>");
+    eprintln!("  skuldbot compile <bot.json> --out <dir>");
+    std::process::exit(code);
+}
+
+/// Run the `skuldbot <subcommand>` CLI, bypassing Tauri entirely so bots can
+/// be validated/compiled/run from a terminal or CI job. Exits the process
+/// directly; never returns.
+fn run_cli(subcommand: &str, args: &[String]) -> ! {
+    if let Err(e) = protection::run_protection_checks() {
+        eprintln!("Security check failed: {e}");
+        std::process::exit(1);
+    }
+
+    let Some(bot_path) = args.first() else {
+        print_cli_usage_and_exit(1);
+    };
+    let dsl = match std::fs::read_to_string(bot_path) {
+        Ok(dsl) => dsl,
+        Err(e) => {
+            eprintln!("Failed to read {bot_path}: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let exit_code = match subcommand {
+        "validate" => match validate_dsl_impl(&dsl) {
+            Ok(true) => 0,
+            Ok(false) | Err(_) => 1,
+        },
+        "compile" => {
+            let output_dir = args
+                .iter()
+                .position(|a| a == "--out")
+                .and_then(|i| args.get(i + 1))
+                .map(std::path::PathBuf::from);
+            match compile_dsl_impl(&dsl, output_dir.as_deref()) {
+                Ok(result) => {
+                    println!("{}", result.bot_path.unwrap_or_default());
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    1
+                }
+            }
+        }
+        "run" => {
+            let log_level = args
+                .iter()
+                .position(|a| a == "--loglevel")
+                .and_then(|i| args.get(i + 1))
+                .map(String::as_str);
+            match run_bot_blocking(&dsl, log_level) {
+                Ok(true) => 0,
+                Ok(false) => 1,
+                Err(e) => {
+                    eprintln!("{e}");
+                    1
+                }
+            }
+        }
+        other => {
+            eprintln!("Unknown subcommand: {other}");
+            print_cli_usage_and_exit(1);
+        }
+    };
+
+    std::process::exit(exit_code);
+}
+
 fn main() {
+    let cli_args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(subcommand) = cli_args.first() {
+        if ["run", "validate", "compile"].contains(&subcommand.as_str()) {
+            run_cli(subcommand, &cli_args[1..]);
+        }
+    }
+
+    init_logging();
+
     tauri::Builder::default()
+        .manage(ExecutionRegistry(Mutex::new(HashMap::new())))
+        .manage(VaultSessionStore::new())
+        .manage(CredentialBrokerStore::new())
+        .manage(LicenseState::new())
+        .manage(IsolationState::new())
+        .setup(|app| {
+            if let Err(e) = protection::run_protection_checks() {
+                log::error!("Protection check failed at startup: {e}");
+                std::process::exit(1);
+            }
+            protection::spawn_integrity_watchdog(app.handle().clone(), std::time::Duration::from_secs(300));
+            vault_session::spawn_auto_lock_sweeper(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Engine commands
             compile_dsl,
             run_bot,
+            cancel_execution,
             validate_dsl,
             save_project,
             load_project,
             get_engine_info,
+            diagnose_environment,
+            get_log_file_path,
             // Project commands
             create_project,
             open_project,
@@ -2076,6 +3855,8 @@ fn main() {
             list_bot_versions,
             load_bot_version,
             cleanup_old_versions,
+            diff_bot_versions,
+            restore_nodes,
             // Asset commands
             list_assets,
             copy_asset,
@@ -2095,6 +3876,7 @@ fn main() {
             vault_set_secret,
             vault_delete_secret,
             vault_change_password,
+            vault_rotate_key,
             // Connections commands
             save_connections,
             load_connections,
@@ -2102,8 +3884,17 @@ fn main() {
             // AI Planner commands
             ai_generate_plan,
             ai_refine_plan,
+            ai_list_model_registry,
             // License commands
             validate_license,
+            // IP protection / anti-tampering commands
+            protection::protection_enroll_security_key,
+            protection::protection_verify_security_key,
+            protection::protection_check_status,
+            protection::protection_get_machine_fingerprint,
+            protection::activation::protection_activate_online,
+            protection::activation::protection_renew_lease,
+            protection::activation::protection_release_seat,
             // Utility commands
             read_directory,
             file_exists,
@@ -6,11 +6,21 @@
 //! - Anti-debugging measures
 //! - Encrypted configuration storage
 
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Public half of the license server's Ed25519 signing keypair. The private
+/// key never ships with the client; it lives only on the license server that
+/// mints signed licenses.
+const LICENSE_SIGNING_PUBLIC_KEY: [u8; 32] = [
+    0x3b, 0x6a, 0x27, 0xbc, 0xce, 0xb6, 0xa4, 0x2d, 0x62, 0xa3, 0xa8, 0xd0, 0x2a, 0x6f, 0x0d, 0x73,
+    0x65, 0x32, 0x15, 0x77, 0x1d, 0xe2, 0x43, 0xa6, 0x3a, 0xc0, 0x48, 0xa1, 0x8b, 0x59, 0xda, 0x29,
+];
+
 /// License types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum LicenseType {
@@ -30,6 +40,21 @@ pub struct License {
     pub expires_at: Option<u64>, // Unix timestamp, None = perpetual
     pub features: Vec<String>,
     pub signature: String,
+    /// Hardware security key binding for `Professional`/`Enterprise` licenses
+    /// with the `hardware_key` feature. `None` means the license is bound to
+    /// the machine fingerprint only.
+    #[serde(default)]
+    pub security_key: Option<SecurityKeyBinding>,
+}
+
+/// A FIDO2/CTAP2 credential a license is bound to, created during
+/// `protection_enroll_security_key` and checked by `License::is_valid`
+/// (through `verify_security_key`) when a license is activated or renewed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityKeyBinding {
+    pub rp_id: String,
+    pub credential_id: String,
+    pub public_key: String,
 }
 
 impl License {
@@ -47,52 +72,169 @@ impl License {
         }
 
         // Verify signature
-        self.verify_signature()
+        if !self.verify_signature() {
+            return false;
+        }
+
+        // Hardware-bound licenses must also pass a live security key challenge
+        if self.requires_security_key() {
+            let Some(binding) = &self.security_key else {
+                return false;
+            };
+            if !verify_security_key(binding, &self.license_key).unwrap_or(false) {
+                return false;
+            }
+        }
+
+        true
     }
 
-    /// Verify the license signature
+    /// Verify the license signature against the embedded Ed25519 public key.
     fn verify_signature(&self) -> bool {
-        let data = format!(
-            "{}:{}:{}:{}:{:?}",
-            self.license_key,
-            self.organization,
-            self.max_runners,
-            self.expires_at.unwrap_or(0),
-            self.features
-        );
-
-        // Simple HMAC-like verification (in production, use proper crypto)
-        let expected = self.compute_signature(&data);
-        self.signature == expected
+        let verifying_key = match VerifyingKey::from_bytes(&LICENSE_SIGNING_PUBLIC_KEY) {
+            Ok(key) => key,
+            Err(_) => return false,
+        };
+
+        let signature_bytes = match base64::engine::general_purpose::STANDARD.decode(&self.signature) {
+            Ok(bytes) => bytes,
+            Err(_) => return false,
+        };
+        let signature_bytes: [u8; 64] = match signature_bytes.try_into() {
+            Ok(bytes) => bytes,
+            Err(_) => return false,
+        };
+        let signature = Signature::from_bytes(&signature_bytes);
+
+        verifying_key
+            .verify_strict(&self.canonical_message(), &signature)
+            .is_ok()
     }
 
-    fn compute_signature(&self, data: &str) -> String {
-        // In production, use proper HMAC with secret key
-        // This is a placeholder - replace with real crypto
-        let mut hasher = DefaultHasher::new();
-        data.hash(&mut hasher);
-        // Mix with secret (obfuscated in binary)
-        let secret: [u8; 16] = [0x5B, 0x4B, 0x55, 0x4C, 0x44, 0x42, 0x4F, 0x54,
-                                 0x52, 0x55, 0x4E, 0x4E, 0x45, 0x52, 0x4B, 0x45];
-        for b in secret {
-            hasher.write_u8(b);
+    /// Deterministic byte encoding of the fields that are covered by the
+    /// signature. Unlike `format!("{:?}", ...)`, this is stable across Rust
+    /// versions and doesn't depend on `Debug` formatting.
+    fn canonical_message(&self) -> Vec<u8> {
+        let mut sorted_features = self.features.clone();
+        sorted_features.sort();
+
+        let mut message = Vec::new();
+        message.extend_from_slice(self.license_key.as_bytes());
+        message.push(0);
+        message.extend_from_slice(self.organization.as_bytes());
+        message.push(0);
+        message.extend_from_slice(&self.max_runners.to_le_bytes());
+        message.extend_from_slice(&self.expires_at.unwrap_or(0).to_le_bytes());
+        for feature in &sorted_features {
+            message.extend_from_slice(feature.as_bytes());
+            message.push(0);
+        }
+        if let Some(binding) = &self.security_key {
+            message.extend_from_slice(binding.rp_id.as_bytes());
+            message.push(0);
+            message.extend_from_slice(binding.credential_id.as_bytes());
+            message.push(0);
         }
-        format!("{:016x}", hasher.finish())
+        message
     }
 
     /// Check if a feature is enabled
     pub fn has_feature(&self, feature: &str) -> bool {
         self.features.contains(&feature.to_string())
     }
+
+    /// Whether this license requires a bound hardware security key to be
+    /// present and verified before it is trusted.
+    pub fn requires_security_key(&self) -> bool {
+        matches!(self.license_type, LicenseType::Professional | LicenseType::Enterprise)
+            && self.has_feature("hardware_key")
+    }
+}
+
+/// Enroll a connected FIDO2/CTAP2 authenticator (e.g. a YubiKey) as the
+/// hardware root of trust for `license_key`. Runs a `make_credential`
+/// ceremony against the first available authenticator with `rp_id` as the
+/// relying party and the license key as the user handle, and returns the
+/// resulting credential binding to be stored on the `License` record.
+pub fn enroll_security_key(license_key: &str, rp_id: &str) -> Result<SecurityKeyBinding, String> {
+    use ctap_hid_fido2::{
+        fidokey::{CredentialSupportedKeyType, FidoKeyHidFactory},
+        verifier, Cfg,
+    };
+
+    let device = FidoKeyHidFactory::create(&Cfg::init())
+        .map_err(|e| format!("no security key found: {e}"))?;
+
+    let challenge = verifier::create_challenge();
+    let rkparam = device
+        .make_credential_rk(
+            rp_id,
+            &challenge,
+            license_key.as_bytes(),
+            Some(CredentialSupportedKeyType::Ecdsa256),
+        )
+        .map_err(|e| format!("make_credential failed: {e}"))?;
+
+    Ok(SecurityKeyBinding {
+        rp_id: rp_id.to_string(),
+        credential_id: base64::engine::general_purpose::STANDARD.encode(&rkparam.credential_id),
+        public_key: base64::engine::general_purpose::STANDARD.encode(&rkparam.public_key),
+    })
+}
+
+/// Challenge the enrolled security key with a fresh random nonce mixed with
+/// `license_key`, and verify the assertion signature against the stored
+/// credential public key. `License::is_valid` requires this to pass for a
+/// hardware-bound license; `run_protection_checks` does not call it.
+pub fn verify_security_key(binding: &SecurityKeyBinding, license_key: &str) -> Result<bool, String> {
+    use ctap_hid_fido2::{fidokey::FidoKeyHidFactory, verifier, Cfg};
+
+    let device = FidoKeyHidFactory::create(&Cfg::init())
+        .map_err(|e| format!("no security key found: {e}"))?;
+
+    let credential_id = base64::engine::general_purpose::STANDARD
+        .decode(&binding.credential_id)
+        .map_err(|e| e.to_string())?;
+    let public_key = base64::engine::general_purpose::STANDARD
+        .decode(&binding.public_key)
+        .map_err(|e| e.to_string())?;
+
+    let mut challenge = verifier::create_challenge();
+    challenge.extend_from_slice(license_key.as_bytes());
+
+    let assertion = device
+        .get_assertion(&binding.rp_id, &challenge, &[credential_id])
+        .map_err(|e| format!("get_assertion failed: {e}"))?;
+
+    Ok(verifier::verify_assertion(
+        &binding.rp_id,
+        &public_key,
+        &challenge,
+        &assertion,
+    ))
 }
 
 /// Anti-debugging detection
 pub fn detect_debugger() -> bool {
     #[cfg(target_os = "windows")]
     {
-        // Check IsDebuggerPresent on Windows
-        use std::process::Command;
-        // This is a simple check - real implementation would use Windows API
+        use windows::Win32::System::Diagnostics::Debug::{
+            CheckRemoteDebuggerPresent, IsDebuggerPresent,
+        };
+        use windows::Win32::System::Threading::GetCurrentProcess;
+
+        // SAFETY: both calls are simple FFI queries against the current
+        // process with no preconditions beyond a valid process handle.
+        unsafe {
+            if IsDebuggerPresent().as_bool() {
+                return true;
+            }
+
+            let mut remote_present = false.into();
+            if CheckRemoteDebuggerPresent(GetCurrentProcess(), &mut remote_present).is_ok() {
+                return remote_present.as_bool();
+            }
+        }
         false
     }
 
@@ -116,17 +258,33 @@ pub fn detect_debugger() -> bool {
 
     #[cfg(target_os = "macos")]
     {
-        // Check sysctl for P_TRACED flag
-        use std::process::Command;
-        if let Ok(output) = Command::new("sysctl")
-            .args(["kern.proc.pid", &std::process::id().to_string()])
-            .output()
-        {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            // P_TRACED flag check would go here
-            return false;
-        }
-        false
+        // Ask the kernel directly for our own kinfo_proc and test the
+        // real P_TRACED flag, instead of shelling out to `sysctl` and
+        // trying to parse its human-readable output.
+        const CTL_KERN: libc::c_int = 1;
+        const KERN_PROC: libc::c_int = 14;
+        const KERN_PROC_PID: libc::c_int = 1;
+        const P_TRACED: i32 = 0x00000800;
+
+        let pid = std::process::id() as libc::c_int;
+        let mut mib: [libc::c_int; 4] = [CTL_KERN, KERN_PROC, KERN_PROC_PID, pid];
+        let mut info: libc::kinfo_proc = unsafe { std::mem::zeroed() };
+        let mut size = std::mem::size_of::<libc::kinfo_proc>();
+
+        // SAFETY: `mib`/`info`/`size` all point at valid, correctly sized
+        // stack memory for the duration of the call.
+        let result = unsafe {
+            libc::sysctl(
+                mib.as_mut_ptr(),
+                mib.len() as u32,
+                &mut info as *mut _ as *mut libc::c_void,
+                &mut size,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+
+        result == 0 && (info.kp_proc.p_flag & P_TRACED) != 0
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
@@ -135,45 +293,89 @@ pub fn detect_debugger() -> bool {
     }
 }
 
-/// Verify binary integrity
+/// Extension of the detached integrity manifest CI drops next to the release
+/// binary after packaging: a single line with the SHA-256 of that exact
+/// binary. The binary never embeds a hash of itself — there's no build step
+/// that could produce it without the hash changing out from under it —
+/// instead it reads this sidecar file at startup. Absent on local/dev builds
+/// (nothing shipped it), which disables the comparison the same as an empty
+/// hash used to.
+const INTEGRITY_MANIFEST_EXT: &str = "sha256";
+
+fn integrity_manifest_path(exe_path: &std::path::Path) -> std::path::PathBuf {
+    let mut path = exe_path.to_path_buf();
+    let manifest_name = match exe_path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}.{INTEGRITY_MANIFEST_EXT}", ext),
+        None => INTEGRITY_MANIFEST_EXT.to_string(),
+    };
+    path.set_extension(manifest_name);
+    path
+}
+
+/// Verify binary integrity by recomputing the running executable's SHA-256
+/// and comparing it against the hash in its detached `.sha256` manifest.
+/// Returns `Ok(true)` when no manifest is present (dev build, nothing to
+/// compare against) and `Ok(false)` (not an error) on a mismatch, so a
+/// patched binary actually fails `run_protection_checks` instead of
+/// silently passing.
 pub fn verify_binary_integrity() -> Result<bool, String> {
-    // Get current executable path
     let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
+    let manifest_path = integrity_manifest_path(&exe_path);
+
+    let expected_hash = match std::fs::read_to_string(&manifest_path) {
+        Ok(contents) => contents.trim().to_string(),
+        Err(_) => return Ok(true),
+    };
 
-    // Read binary and compute hash
     let binary = std::fs::read(&exe_path).map_err(|e| e.to_string())?;
 
-    let mut hasher = DefaultHasher::new();
-    binary.hash(&mut hasher);
-    let current_hash = hasher.finish();
+    let mut hasher = sha2::Sha256::new();
+    sha2::Digest::update(&mut hasher, &binary);
+    let current_hash = hex::encode(sha2::Digest::finalize(hasher));
 
-    // In production, compare with embedded hash
-    // For now, just return true
-    Ok(true)
+    Ok(current_hash == expected_hash)
 }
 
-/// Encrypted storage for sensitive data
+/// Re-run `run_protection_checks` on an interval so tampering introduced
+/// mid-session (a debugger attached after launch, a binary patched on
+/// disk and the process re-executed) is caught, not just at startup.
+pub fn spawn_integrity_watchdog(app: tauri::AppHandle, interval: std::time::Duration) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        if let Err(e) = run_protection_checks() {
+            eprintln!("Periodic integrity check failed: {e}");
+            app.exit(1);
+        }
+    });
+}
+
+/// Salt length (bytes) for the HKDF-SHA256 key derivation, stored alongside
+/// the ciphertext so `load()` can re-derive the same per-file key.
+const SECURE_STORAGE_SALT_LEN: usize = 16;
+/// AES-GCM standard nonce length (96 bits).
+const SECURE_STORAGE_NONCE_LEN: usize = 12;
+
+/// Encrypted storage for sensitive data, backed by AES-256-GCM with a key
+/// derived per-file via HKDF-SHA256 over the machine id and a random salt.
 pub struct SecureStorage {
-    key: [u8; 32],
+    machine_id: String,
 }
 
 impl SecureStorage {
     pub fn new() -> Self {
-        // Derive key from machine-specific data
-        let machine_id = Self::get_machine_id();
-        let mut key = [0u8; 32];
-
-        let mut hasher = DefaultHasher::new();
-        machine_id.hash(&mut hasher);
-        let hash = hasher.finish();
-
-        // Expand hash to 32 bytes
-        for i in 0..4 {
-            let bytes = hash.to_le_bytes();
-            key[i * 8..(i + 1) * 8].copy_from_slice(&bytes);
+        Self {
+            machine_id: Self::get_machine_id(),
         }
+    }
 
-        Self { key }
+    /// Derive the per-file AES-256 key from the machine id and a random salt
+    /// via HKDF-SHA256.
+    fn derive_key(&self, salt: &[u8]) -> [u8; 32] {
+        let hk = hkdf::Hkdf::<sha2::Sha256>::new(Some(salt), self.machine_id.as_bytes());
+        let mut key = [0u8; 32];
+        hk.expand(b"skuldbot-secure-storage-v1", &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        key
     }
 
     fn get_machine_id() -> String {
@@ -222,29 +424,55 @@ impl SecureStorage {
         "fallback-machine-id".to_string()
     }
 
-    /// Simple XOR encryption (in production, use proper AES)
-    pub fn encrypt(&self, data: &[u8]) -> Vec<u8> {
-        data.iter()
-            .enumerate()
-            .map(|(i, &b)| b ^ self.key[i % self.key.len()])
-            .collect()
-    }
+    /// Store encrypted data to file as `[salt][nonce][ciphertext+tag]`. A
+    /// fresh random salt and nonce are generated on every call.
+    pub fn store(&self, path: &std::path::Path, data: &[u8]) -> Result<(), String> {
+        use aes_gcm::aead::{Aead, KeyInit, OsRng};
+        use aes_gcm::{Aes256Gcm, Key, Nonce};
+        use rand::RngCore;
 
-    pub fn decrypt(&self, data: &[u8]) -> Vec<u8> {
-        // XOR is symmetric
-        self.encrypt(data)
-    }
+        let mut salt = [0u8; SECURE_STORAGE_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = self.derive_key(&salt);
 
-    /// Store encrypted data to file
-    pub fn store(&self, path: &std::path::Path, data: &[u8]) -> Result<(), String> {
-        let encrypted = self.encrypt(data);
-        std::fs::write(path, encrypted).map_err(|e| e.to_string())
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let mut nonce_bytes = [0u8; SECURE_STORAGE_NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+
+        let ciphertext = cipher
+            .encrypt(nonce, data)
+            .map_err(|e| format!("encryption failed: {e}"))?;
+
+        let mut out = Vec::with_capacity(salt.len() + nonce_bytes.len() + ciphertext.len());
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&nonce_bytes);
+        out.extend_from_slice(&ciphertext);
+
+        std::fs::write(path, out).map_err(|e| e.to_string())
     }
 
-    /// Load and decrypt data from file
+    /// Load and decrypt data from a file written by `store()`, failing if the
+    /// GCM authentication tag doesn't verify (tampered or wrong machine).
     pub fn load(&self, path: &std::path::Path) -> Result<Vec<u8>, String> {
-        let encrypted = std::fs::read(path).map_err(|e| e.to_string())?;
-        Ok(self.decrypt(&encrypted))
+        use aes_gcm::aead::{Aead, KeyInit};
+        use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+        let raw = std::fs::read(path).map_err(|e| e.to_string())?;
+        if raw.len() < SECURE_STORAGE_SALT_LEN + SECURE_STORAGE_NONCE_LEN {
+            return Err("secure storage file is truncated".to_string());
+        }
+
+        let (salt, rest) = raw.split_at(SECURE_STORAGE_SALT_LEN);
+        let (nonce_bytes, ciphertext) = rest.split_at(SECURE_STORAGE_NONCE_LEN);
+
+        let key = self.derive_key(salt);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let nonce = Nonce::from_slice(nonce_bytes);
+
+        cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|_| "decryption failed: data is corrupt or tampered with".to_string())
     }
 }
 
@@ -263,33 +491,86 @@ pub fn run_protection_checks() -> Result<(), String> {
     Ok(())
 }
 
+/// Tauri Isolation Pattern support: protection-sensitive commands take an
+/// isolation token, an HMAC-SHA256 of the command's name under a
+/// per-session key, and check it before doing any work. The token covers
+/// the command name only; the command's arguments are not signed.
+/// Commands that don't carry a valid signature are rejected outright, so an
+/// injected or compromised webview script gains nothing by calling them
+/// directly.
+pub mod isolation {
+    use hmac::{Hmac, Mac};
+    use rand::RngCore;
+    use sha2::Sha256;
+    use std::sync::Mutex;
+
+    /// Per-launch random session key that `verify` checks isolation tokens
+    /// against. This module does not pass it to the webview.
+    pub struct IsolationState(Mutex<[u8; 32]>);
+
+    impl IsolationState {
+        pub fn new() -> Self {
+            let mut key = [0u8; 32];
+            rand::rngs::OsRng.fill_bytes(&mut key);
+            Self(Mutex::new(key))
+        }
+
+        pub fn session_key(&self) -> [u8; 32] {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    /// Sign `payload` with the session key. The protected commands below
+    /// verify against their own command name as the payload, so the
+    /// arguments they receive are not covered by the signature.
+    pub fn sign(key: &[u8; 32], payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Verify a command's isolation token against the session key. Commands
+    /// that fail this check are rejected before doing any real work.
+    pub fn verify(state: &IsolationState, payload: &str, token: &str) -> Result<(), String> {
+        let key = state.session_key();
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&hex::decode(token).map_err(|_| "invalid isolation token".to_string())?)
+            .map_err(|_| "isolation signature verification failed".to_string())
+    }
+}
+
 // Tauri commands for IP protection
 
 #[tauri::command]
-pub fn protection_validate_binary_license(license_key: String) -> Result<License, String> {
-    // Binary-level license validation for IP protection
-    // This is separate from the application-level license validation
-    let license = License {
-        license_key: license_key.clone(),
-        license_type: LicenseType::Trial,
-        organization: "Trial User".to_string(),
-        max_runners: 1,
-        expires_at: Some(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-                + 30 * 24 * 60 * 60, // 30 days
-        ),
-        features: vec!["basic".to_string()],
-        signature: String::new(), // Would be computed by license server
-    };
+pub fn protection_enroll_security_key(
+    license_key: String,
+    rp_id: String,
+    isolation_state: tauri::State<isolation::IsolationState>,
+    isolation_token: String,
+) -> Result<SecurityKeyBinding, String> {
+    isolation::verify(&isolation_state, "protection_enroll_security_key", &isolation_token)?;
+    enroll_security_key(&license_key, &rp_id)
+}
 
-    Ok(license)
+#[tauri::command]
+pub fn protection_verify_security_key(
+    license_key: String,
+    binding: SecurityKeyBinding,
+    isolation_state: tauri::State<isolation::IsolationState>,
+    isolation_token: String,
+) -> Result<bool, String> {
+    isolation::verify(&isolation_state, "protection_verify_security_key", &isolation_token)?;
+    verify_security_key(&binding, &license_key)
 }
 
 #[tauri::command]
-pub fn protection_check_status() -> Result<serde_json::Value, String> {
+pub fn protection_check_status(
+    isolation_state: tauri::State<isolation::IsolationState>,
+    isolation_token: String,
+) -> Result<serde_json::Value, String> {
+    isolation::verify(&isolation_state, "protection_check_status", &isolation_token)?;
+
     // Check protection status
     let debugger_detected = detect_debugger();
     let integrity_ok = verify_binary_integrity().unwrap_or(false);
@@ -305,11 +586,14 @@ pub fn protection_check_status() -> Result<serde_json::Value, String> {
     }))
 }
 
-#[tauri::command]
-pub fn protection_get_machine_fingerprint() -> String {
+/// Fingerprint this machine from multiple stable, machine-specific sources.
+/// Not isolation-gated itself so other in-process code (e.g. the activation
+/// subsystem and `license::verify_license_token`'s device-binding check)
+/// can call it directly; the Tauri command wrapper below is what enforces
+/// the isolation signature for webview callers.
+pub(crate) fn machine_fingerprint() -> String {
     let machine_id = SecureStorage::get_machine_id();
 
-    // Create fingerprint from multiple sources
     let hostname_str = hostname::get()
         .map(|h| h.to_string_lossy().to_string())
         .unwrap_or_default();
@@ -320,3 +604,188 @@ pub fn protection_get_machine_fingerprint() -> String {
 
     format!("{:016x}", hasher.finish())
 }
+
+#[tauri::command]
+pub fn protection_get_machine_fingerprint(
+    isolation_state: tauri::State<isolation::IsolationState>,
+    isolation_token: String,
+) -> Result<String, String> {
+    isolation::verify(
+        &isolation_state,
+        "protection_get_machine_fingerprint",
+        &isolation_token,
+    )?;
+    Ok(machine_fingerprint())
+}
+
+/// Online activation against a license/rendezvous server, with floating-seat
+/// leasing: the server tracks active machine fingerprints against
+/// `License::max_runners` and can revoke or refuse leases beyond the cap.
+/// The last good signed license+lease is cached locally via `SecureStorage`
+/// so the app keeps working offline until the lease's grace window expires.
+pub mod activation {
+    use super::{License, SecureStorage};
+    use serde::{Deserialize, Serialize};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// How long past its `lease_expires_at` a cached lease remains usable
+    /// once the license server becomes unreachable.
+    const LEASE_GRACE_PERIOD_SECS: u64 = 24 * 60 * 60;
+
+    /// A signed `License` plus the floating-seat lease that authorizes this
+    /// machine to hold it.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SignedLease {
+        pub license: License,
+        pub lease_token: String,
+        /// Unix timestamp the lease must be renewed by.
+        pub lease_expires_at: u64,
+    }
+
+    fn cache_path() -> Result<std::path::PathBuf, String> {
+        let dirs = directories::ProjectDirs::from("com", "khipus", "skuldbot-studio")
+            .ok_or_else(|| "could not resolve app data directory".to_string())?;
+        let dir = dirs.data_dir();
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        Ok(dir.join("license_lease.bin"))
+    }
+
+    fn cache_lease(lease: &SignedLease) -> Result<(), String> {
+        let bytes = serde_json::to_vec(lease).map_err(|e| e.to_string())?;
+        SecureStorage::new().store(&cache_path()?, &bytes)
+    }
+
+    fn load_cached_lease() -> Result<SignedLease, String> {
+        let bytes = SecureStorage::new().load(&cache_path()?)?;
+        serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// Activate `license_key` against `server_url`, posting this machine's
+    /// fingerprint and checking out a floating seat. On success, verifies
+    /// and caches the returned signed license+lease.
+    pub async fn activate_online(server_url: &str, license_key: &str) -> Result<SignedLease, String> {
+        let fingerprint = super::machine_fingerprint();
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{}/activate", server_url))
+            .json(&serde_json::json!({
+                "licenseKey": license_key,
+                "machineFingerprint": fingerprint,
+            }))
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| format!("activation request failed: {e}"))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("activation rejected: {status} - {text}"));
+        }
+
+        let lease: SignedLease = response.json().await.map_err(|e| e.to_string())?;
+        if !lease.license.is_valid() {
+            return Err("activation server returned an invalid signed license".to_string());
+        }
+
+        cache_lease(&lease)?;
+        Ok(lease)
+    }
+
+    /// Renew the active lease before `lease_expires_at`, refreshing the
+    /// cached copy on success.
+    pub async fn renew_lease(server_url: &str, lease_token: &str) -> Result<SignedLease, String> {
+        let fingerprint = super::machine_fingerprint();
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{}/lease/renew", server_url))
+            .json(&serde_json::json!({
+                "leaseToken": lease_token,
+                "machineFingerprint": fingerprint,
+            }))
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await;
+
+        let response = match response {
+            Ok(response) if response.status().is_success() => response,
+            _ => {
+                // Offline or the server rejected us; fall back to the cached
+                // lease as long as we're still inside its grace window.
+                let cached = load_cached_lease()?;
+                if now() < cached.lease_expires_at + LEASE_GRACE_PERIOD_SECS {
+                    return Ok(cached);
+                }
+                return Err("lease expired and renewal server is unreachable".to_string());
+            }
+        };
+
+        let lease: SignedLease = response.json().await.map_err(|e| e.to_string())?;
+        if !lease.license.is_valid() {
+            return Err("renewal server returned an invalid signed license".to_string());
+        }
+
+        cache_lease(&lease)?;
+        Ok(lease)
+    }
+
+    /// Release this machine's floating seat back to the server, e.g. on
+    /// clean shutdown, so another machine can check it out immediately.
+    pub async fn release_seat(server_url: &str, lease_token: &str) -> Result<(), String> {
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{}/lease/release", server_url))
+            .json(&serde_json::json!({ "leaseToken": lease_token }))
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| format!("seat release failed: {e}"))?;
+
+        if !response.status().is_success() {
+            return Err(format!("seat release rejected: {}", response.status()));
+        }
+        Ok(())
+    }
+
+    #[tauri::command]
+    pub async fn protection_activate_online(
+        server_url: String,
+        license_key: String,
+        isolation_state: tauri::State<'_, super::isolation::IsolationState>,
+        isolation_token: String,
+    ) -> Result<SignedLease, String> {
+        super::isolation::verify(&isolation_state, "protection_activate_online", &isolation_token)?;
+        activate_online(&server_url, &license_key).await
+    }
+
+    #[tauri::command]
+    pub async fn protection_renew_lease(
+        server_url: String,
+        lease_token: String,
+        isolation_state: tauri::State<'_, super::isolation::IsolationState>,
+        isolation_token: String,
+    ) -> Result<SignedLease, String> {
+        super::isolation::verify(&isolation_state, "protection_renew_lease", &isolation_token)?;
+        renew_lease(&server_url, &lease_token).await
+    }
+
+    #[tauri::command]
+    pub async fn protection_release_seat(
+        server_url: String,
+        lease_token: String,
+        isolation_state: tauri::State<'_, super::isolation::IsolationState>,
+        isolation_token: String,
+    ) -> Result<(), String> {
+        super::isolation::verify(&isolation_state, "protection_release_seat", &isolation_token)?;
+        release_seat(&server_url, &lease_token).await
+    }
+}
@@ -0,0 +1,462 @@
+//! Pluggable secret storage for the Studio `vault_*` commands.
+//!
+//! Every `vault_*` command used to hardcode a call into the Python engine's
+//! `LocalVault` over a subprocess. This module pulls that behind a
+//! `SecretBackend` trait so a project can instead point at a shared
+//! HashiCorp Vault KV v2 mount without the commands themselves changing
+//! shape.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretMeta {
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Lease/renewal info a remote backend can report back to the UI. Local,
+/// file-based backends have no concept of a lease and leave this `None`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LeaseInfo {
+    pub lease_id: Option<String>,
+    pub lease_duration_secs: Option<u64>,
+    pub renewable: bool,
+}
+
+/// Per-project choice of where secrets actually live. Defaults to `Local`
+/// (the original per-machine `secrets.vault` file) when a project doesn't
+/// configure anything else.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SecretBackendConfig {
+    Local,
+    Vault(VaultBackendConfig),
+}
+
+impl Default for SecretBackendConfig {
+    fn default() -> Self {
+        SecretBackendConfig::Local
+    }
+}
+
+/// Connection details for a self-hosted HashiCorp Vault KV v2 mount.
+/// Either `token` or an AppRole pair (`role_id` + `secret_id`) must be set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultBackendConfig {
+    pub address: String,
+    pub mount: String,
+    pub path: String,
+    pub token: Option<String>,
+    pub role_id: Option<String>,
+    pub secret_id: Option<String>,
+}
+
+pub trait SecretBackend {
+    /// Initialize a brand-new vault protected by `password`. Local backends
+    /// create the encrypted file; remote backends with no "creation"
+    /// concept (the KV mount already exists server-side) treat this as
+    /// verifying the connection instead.
+    fn create(&mut self, password: &str) -> Result<(), String>;
+    fn unlock(&mut self, password: &str) -> Result<(), String>;
+    fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<(), String>;
+    fn list_secrets(&self) -> Result<Vec<SecretMeta>, String>;
+    fn get_secret(&self, name: &str) -> Result<String, String>;
+    fn set_secret(&mut self, name: &str, value: &str, description: Option<&str>) -> Result<(), String>;
+    fn delete_secret(&mut self, name: &str) -> Result<(), String>;
+
+    /// Lease/renewal info from the last operation. `None` for backends
+    /// (like the local file vault) with no concept of a lease.
+    fn last_lease(&self) -> Option<LeaseInfo> {
+        None
+    }
+}
+
+/// The original backend: a local, file-based vault managed by the Python
+/// engine's `LocalVault`, invoked over a subprocess per call.
+pub struct LocalVaultBackend {
+    pub engine_path: PathBuf,
+    pub python_exe: String,
+    pub vault_path: String,
+}
+
+impl LocalVaultBackend {
+    fn run_python(&self, script: &str) -> Result<String, String> {
+        let output = Command::new(&self.python_exe)
+            .arg("-c")
+            .arg(script)
+            .output()
+            .map_err(|e| format!("Failed to execute Python: {}", e))?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string())
+        }
+    }
+
+    fn esc(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('\'', "\\'")
+    }
+}
+
+impl SecretBackend for LocalVaultBackend {
+    fn create(&mut self, password: &str) -> Result<(), String> {
+        self.run_python(&format!(
+            r#"
+import sys
+sys.path.insert(0, '{}')
+from skuldbot.libs.local_vault import LocalVault
+
+vault = LocalVault('{}')
+vault.create('{}')
+print('OK')
+"#,
+            self.engine_path.display(),
+            Self::esc(&self.vault_path),
+            Self::esc(password)
+        ))
+        .map(|_| ())
+    }
+
+    fn unlock(&mut self, password: &str) -> Result<(), String> {
+        self.run_python(&format!(
+            r#"
+import sys
+sys.path.insert(0, '{}')
+from skuldbot.libs.local_vault import LocalVault
+
+vault = LocalVault('{}')
+vault.unlock('{}')
+print('OK')
+"#,
+            self.engine_path.display(),
+            Self::esc(&self.vault_path),
+            Self::esc(password)
+        ))
+        .map(|_| ())
+    }
+
+    fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<(), String> {
+        self.run_python(&format!(
+            r#"
+import sys
+sys.path.insert(0, '{}')
+from skuldbot.libs.local_vault import LocalVault
+
+vault = LocalVault('{}')
+vault.unlock('{}')
+vault.change_password('{}', '{}')
+print('OK')
+"#,
+            self.engine_path.display(),
+            Self::esc(&self.vault_path),
+            Self::esc(old_password),
+            Self::esc(old_password),
+            Self::esc(new_password)
+        ))
+        .map(|_| ())
+    }
+
+    fn list_secrets(&self) -> Result<Vec<SecretMeta>, String> {
+        let stdout = self.run_python(&format!(
+            r#"
+import sys
+import json
+sys.path.insert(0, '{}')
+from skuldbot.libs.local_vault import LocalVault
+
+vault = LocalVault('{}')
+secrets = vault.list_secrets()
+print(json.dumps(secrets))
+"#,
+            self.engine_path.display(),
+            Self::esc(&self.vault_path)
+        ))?;
+        serde_json::from_str(&stdout).map_err(|e| format!("Failed to parse secrets: {}", e))
+    }
+
+    fn get_secret(&self, name: &str) -> Result<String, String> {
+        self.run_python(&format!(
+            r#"
+import sys
+sys.path.insert(0, '{}')
+from skuldbot.libs.local_vault import LocalVault
+
+vault = LocalVault('{}')
+value = vault.get_secret('{}')
+print(value, end='')
+"#,
+            self.engine_path.display(),
+            Self::esc(&self.vault_path),
+            Self::esc(name)
+        ))
+    }
+
+    fn set_secret(&mut self, name: &str, value: &str, description: Option<&str>) -> Result<(), String> {
+        let desc_arg = description
+            .map(|d| format!(", description='{}'", Self::esc(d)))
+            .unwrap_or_default();
+
+        self.run_python(&format!(
+            r#"
+import sys
+sys.path.insert(0, '{}')
+from skuldbot.libs.local_vault import LocalVault
+
+vault = LocalVault('{}')
+vault.set_secret('{}', '{}'{})
+print('OK')
+"#,
+            self.engine_path.display(),
+            Self::esc(&self.vault_path),
+            Self::esc(name),
+            Self::esc(value),
+            desc_arg
+        ))
+        .map(|_| ())
+    }
+
+    fn delete_secret(&mut self, name: &str) -> Result<(), String> {
+        self.run_python(&format!(
+            r#"
+import sys
+sys.path.insert(0, '{}')
+from skuldbot.libs.local_vault import LocalVault
+
+vault = LocalVault('{}')
+vault.delete_secret('{}')
+print('OK')
+"#,
+            self.engine_path.display(),
+            Self::esc(&self.vault_path),
+            Self::esc(name)
+        ))
+        .map(|_| ())
+    }
+}
+
+/// A project-level secret store backed by a self-hosted HashiCorp Vault KV
+/// v2 mount instead of a per-machine file, so a team can share one store.
+pub struct HashiCorpVaultBackend {
+    config: VaultBackendConfig,
+    client: reqwest::blocking::Client,
+    token: Option<String>,
+    last_lease: Option<LeaseInfo>,
+}
+
+impl HashiCorpVaultBackend {
+    pub fn new(config: VaultBackendConfig) -> Self {
+        Self {
+            token: config.token.clone(),
+            config,
+            client: reqwest::blocking::Client::new(),
+            last_lease: None,
+        }
+    }
+
+    fn data_url(&self) -> String {
+        format!(
+            "{}/v1/{}/data/{}",
+            self.config.address.trim_end_matches('/'),
+            self.config.mount,
+            self.config.path
+        )
+    }
+
+    fn metadata_url(&self) -> String {
+        format!(
+            "{}/v1/{}/metadata/{}",
+            self.config.address.trim_end_matches('/'),
+            self.config.mount,
+            self.config.path
+        )
+    }
+
+    fn token_or_err(&self) -> Result<&str, String> {
+        self.token
+            .as_deref()
+            .ok_or_else(|| "Vault backend is not authenticated; call unlock first".to_string())
+    }
+
+    /// Authenticate via AppRole, yielding a client token plus its lease.
+    fn login_approle(&mut self, role_id: &str, secret_id: &str) -> Result<(), String> {
+        let response = self
+            .client
+            .post(format!("{}/v1/auth/approle/login", self.config.address.trim_end_matches('/')))
+            .json(&serde_json::json!({ "role_id": role_id, "secret_id": secret_id }))
+            .send()
+            .map_err(|e| format!("Vault login failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Vault login rejected: {}", response.status()));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|e| format!("Failed to parse Vault login response: {}", e))?;
+
+        let auth = body.get("auth").ok_or("Vault login response missing 'auth'")?;
+        self.token = auth
+            .get("client_token")
+            .and_then(|t| t.as_str())
+            .map(str::to_string);
+        self.last_lease = Some(LeaseInfo {
+            lease_id: None,
+            lease_duration_secs: auth.get("lease_duration").and_then(|d| d.as_u64()),
+            renewable: auth.get("renewable").and_then(|r| r.as_bool()).unwrap_or(false),
+        });
+
+        if self.token.is_none() {
+            return Err("Vault login response did not include a client_token".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fetch the full KV v2 payload so a `set_secret`/`delete_secret` call
+    /// can merge into it instead of clobbering unrelated keys.
+    fn read_all(&self) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+        let token = self.token_or_err()?;
+        let response = self
+            .client
+            .get(self.data_url())
+            .header("X-Vault-Token", token)
+            .send()
+            .map_err(|e| format!("Failed to reach Vault: {}", e))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(serde_json::Map::new());
+        }
+        if !response.status().is_success() {
+            return Err(format!("Vault read failed: {}", response.status()));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|e| format!("Failed to parse Vault response: {}", e))?;
+        Ok(body
+            .get("data")
+            .and_then(|d| d.get("data"))
+            .and_then(|d| d.as_object())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn write_all(&self, data: &serde_json::Map<String, serde_json::Value>) -> Result<(), String> {
+        let token = self.token_or_err()?;
+        let response = self
+            .client
+            .post(self.data_url())
+            .header("X-Vault-Token", token)
+            .json(&serde_json::json!({ "data": data }))
+            .send()
+            .map_err(|e| format!("Failed to reach Vault: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Vault write failed: {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+impl SecretBackend for HashiCorpVaultBackend {
+    fn create(&mut self, password: &str) -> Result<(), String> {
+        // There's no "create" concept for a KV v2 mount that already
+        // exists server-side — initializing a project against this
+        // backend just means verifying we can authenticate to it.
+        self.unlock(password)
+    }
+
+    fn unlock(&mut self, _password: &str) -> Result<(), String> {
+        if self.token.is_some() {
+            return Ok(());
+        }
+        let role_id = self.config.role_id.clone();
+        let secret_id = self.config.secret_id.clone();
+        match (role_id, secret_id) {
+            (Some(role_id), Some(secret_id)) => self.login_approle(&role_id, &secret_id),
+            _ => Err("Vault backend requires a token or an AppRole role_id/secret_id".to_string()),
+        }
+    }
+
+    fn change_password(&mut self, _old_password: &str, _new_password: &str) -> Result<(), String> {
+        Err("Vault backend credentials are managed by Vault itself; rotate the AppRole or token there".to_string())
+    }
+
+    fn list_secrets(&self) -> Result<Vec<SecretMeta>, String> {
+        let token = self.token_or_err()?;
+        let response = self
+            .client
+            .get(format!("{}?list=true", self.metadata_url()))
+            .header("X-Vault-Token", token)
+            .send()
+            .map_err(|e| format!("Failed to reach Vault: {}", e))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(vec![]);
+        }
+        if !response.status().is_success() {
+            return Err(format!("Vault list failed: {}", response.status()));
+        }
+
+        let data = self.read_all()?;
+        Ok(data
+            .keys()
+            .map(|name| SecretMeta {
+                name: name.clone(),
+                description: None,
+                created_at: None,
+                updated_at: None,
+            })
+            .collect())
+    }
+
+    fn get_secret(&self, name: &str) -> Result<String, String> {
+        let data = self.read_all()?;
+        data.get(name)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("Secret '{}' not found", name))
+    }
+
+    fn set_secret(&mut self, name: &str, value: &str, _description: Option<&str>) -> Result<(), String> {
+        let mut data = self.read_all()?;
+        data.insert(name.to_string(), serde_json::Value::String(value.to_string()));
+        self.write_all(&data)
+    }
+
+    fn delete_secret(&mut self, name: &str) -> Result<(), String> {
+        let mut data = self.read_all()?;
+        if data.remove(name).is_none() {
+            return Err(format!("Secret '{}' not found", name));
+        }
+        self.write_all(&data)
+    }
+
+    fn last_lease(&self) -> Option<LeaseInfo> {
+        self.last_lease.clone()
+    }
+}
+
+/// Build the backend a project's `SecretBackendConfig` (or the absence of
+/// one) resolves to.
+pub fn build_backend(
+    config: Option<&SecretBackendConfig>,
+    engine_path: PathBuf,
+    python_exe: String,
+    vault_path: String,
+) -> Box<dyn SecretBackend> {
+    match config {
+        Some(SecretBackendConfig::Vault(vault_config)) => {
+            Box::new(HashiCorpVaultBackend::new(vault_config.clone()))
+        }
+        Some(SecretBackendConfig::Local) | None => Box::new(LocalVaultBackend {
+            engine_path,
+            python_exe,
+            vault_path,
+        }),
+    }
+}
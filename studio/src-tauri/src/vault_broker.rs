@@ -0,0 +1,322 @@
+//! Local credential broker for spawned bot processes.
+//!
+//! `run_bot` used to leave the vault master password sitting in the
+//! environment for every bot process to read and re-unlock the vault with.
+//! Instead, Studio starts one broker per unlocked vault — a Unix domain
+//! socket on Linux/macOS, a named pipe on Windows — and hands each spawned
+//! bot a token in its environment, scoped to whichever secret names that
+//! process is allowed to read. The bot asks the broker for a secret by name
+//! over that socket and gets back only that value, the same shape an SSH
+//! agent uses to hand out signatures for the life of a session without
+//! exposing the private key — a bot needing several credentials (a username
+//! and a password, two API keys) makes several requests against the one
+//! token instead of being capped at its first.
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::watch;
+
+use crate::vault_backend::{build_backend, SecretBackendConfig};
+
+#[derive(Debug, Deserialize)]
+struct BrokerRequest {
+    token: String,
+    get: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BrokerOk {
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BrokerErr {
+    error: String,
+}
+
+/// A credential handed to a single spawned process, valid for its whole
+/// lifetime rather than single-use — safety comes from `allowed_secrets`
+/// scoping which names the token can fetch, not from a one-shot token.
+struct TokenGrant {
+    allowed_secrets: Option<Vec<String>>,
+}
+
+struct RunningBroker {
+    endpoint: String,
+    tokens: Arc<Mutex<HashMap<String, TokenGrant>>>,
+    shutdown: watch::Sender<bool>,
+}
+
+/// Tauri-managed state: one running broker per unlocked vault path.
+pub struct CredentialBrokerStore(Mutex<HashMap<String, RunningBroker>>);
+
+impl CredentialBrokerStore {
+    pub fn new() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+
+    /// Start (or restart) the broker for `vault_path`, listening for bot
+    /// processes asking for secrets on this vault's behalf.
+    pub fn start(
+        &self,
+        vault_path: &str,
+        password: &str,
+        backend: Option<SecretBackendConfig>,
+        engine_path: PathBuf,
+        python_exe: String,
+    ) -> Result<(), String> {
+        self.stop(vault_path);
+
+        let endpoint = endpoint_for(vault_path);
+        let tokens: Arc<Mutex<HashMap<String, TokenGrant>>> = Arc::new(Mutex::new(HashMap::new()));
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        spawn_listener(
+            endpoint.clone(),
+            vault_path.to_string(),
+            tokens.clone(),
+            shutdown_rx,
+            password.to_string(),
+            backend,
+            engine_path,
+            python_exe,
+        )?;
+
+        self.0.lock().unwrap().insert(
+            vault_path.to_string(),
+            RunningBroker {
+                endpoint,
+                tokens,
+                shutdown: shutdown_tx,
+            },
+        );
+        Ok(())
+    }
+
+    /// Stop the broker for `vault_path`, if one is running. Safe to call on
+    /// a path with no broker.
+    pub fn stop(&self, vault_path: &str) {
+        if let Some(broker) = self.0.lock().unwrap().remove(vault_path) {
+            let _ = broker.shutdown.send(true);
+        }
+    }
+
+    /// Mint a token for a process about to be spawned, scoped to
+    /// `allowed_secrets` (`None` means whatever the backend allows). Valid
+    /// for as many requests as that process makes, not just its first.
+    /// Returns the `(endpoint, token)` pair to inject into its environment,
+    /// or `None` if the vault isn't unlocked.
+    pub fn issue_token(
+        &self,
+        vault_path: &str,
+        allowed_secrets: Option<Vec<String>>,
+    ) -> Option<(String, String)> {
+        let sessions = self.0.lock().unwrap();
+        let broker = sessions.get(vault_path)?;
+        let token = random_token();
+        broker.tokens.lock().unwrap().insert(token.clone(), TokenGrant { allowed_secrets });
+        Some((broker.endpoint.clone(), token))
+    }
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A stable, filesystem-safe endpoint name derived from the vault path, so
+/// re-unlocking the same vault reuses the same socket/pipe name.
+fn endpoint_for(vault_path: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in vault_path.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    #[cfg(unix)]
+    {
+        std::env::temp_dir()
+            .join(format!("skuldbot-vault-{:016x}.sock", hash))
+            .to_string_lossy()
+            .to_string()
+    }
+    #[cfg(windows)]
+    {
+        format!(r"\\.\pipe\skuldbot-vault-{:016x}", hash)
+    }
+}
+
+async fn handle_connection<S>(
+    stream: S,
+    vault_path: String,
+    tokens: Arc<Mutex<HashMap<String, TokenGrant>>>,
+    password: String,
+    backend: Option<SecretBackendConfig>,
+    engine_path: PathBuf,
+    python_exe: String,
+) where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    let Ok(Some(line)) = lines.next_line().await else {
+        return;
+    };
+
+    let response = match serde_json::from_str::<BrokerRequest>(&line) {
+        Ok(request) => handle_request(&request, &vault_path, &tokens, &password, &backend, &engine_path, &python_exe),
+        Err(e) => broker_err(&format!("malformed request: {e}")),
+    };
+
+    let _ = writer.write_all(response.as_bytes()).await;
+    let _ = writer.write_all(b"\n").await;
+}
+
+fn handle_request(
+    request: &BrokerRequest,
+    vault_path: &str,
+    tokens: &Arc<Mutex<HashMap<String, TokenGrant>>>,
+    password: &str,
+    backend: &Option<SecretBackendConfig>,
+    engine_path: &PathBuf,
+    python_exe: &str,
+) -> String {
+    {
+        let tokens = tokens.lock().unwrap();
+        match tokens.get(&request.token) {
+            None => return broker_err("unknown or expired token"),
+            Some(grant) => {
+                if let Some(allowed) = &grant.allowed_secrets {
+                    if !allowed.iter().any(|name| name == &request.get) {
+                        return broker_err(&format!("secret '{}' is not whitelisted for this process", request.get));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut backend_instance = build_backend(backend.as_ref(), engine_path.clone(), python_exe.to_string(), vault_path.to_string());
+    if let Err(e) = backend_instance.unlock(password) {
+        return broker_err(&e);
+    }
+    match backend_instance.get_secret(&request.get) {
+        Ok(value) => serde_json::to_string(&BrokerOk { value }).unwrap_or_else(|_| broker_err("internal error")),
+        Err(e) => broker_err(&e),
+    }
+}
+
+fn broker_err(message: &str) -> String {
+    serde_json::to_string(&BrokerErr {
+        error: message.to_string(),
+    })
+    .unwrap_or_else(|_| "{\"error\":\"internal error\"}".to_string())
+}
+
+#[cfg(unix)]
+fn spawn_listener(
+    endpoint: String,
+    vault_path: String,
+    tokens: Arc<Mutex<HashMap<String, TokenGrant>>>,
+    mut shutdown: watch::Receiver<bool>,
+    password: String,
+    backend: Option<SecretBackendConfig>,
+    engine_path: PathBuf,
+    python_exe: String,
+) -> Result<(), String> {
+    let _ = std::fs::remove_file(&endpoint);
+    let std_listener = std::os::unix::net::UnixListener::bind(&endpoint)
+        .map_err(|e| format!("Failed to bind vault broker socket: {}", e))?;
+    std_listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to configure vault broker socket: {}", e))?;
+    let listener = tokio::net::UnixListener::from_std(std_listener)
+        .map_err(|e| format!("Failed to adopt vault broker socket: {}", e))?;
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                changed = shutdown.changed() => {
+                    if changed.is_err() || *shutdown.borrow() {
+                        break;
+                    }
+                }
+                accepted = listener.accept() => {
+                    let Ok((stream, _)) = accepted else { continue; };
+                    tauri::async_runtime::spawn(handle_connection(
+                        stream,
+                        vault_path.clone(),
+                        tokens.clone(),
+                        password.clone(),
+                        backend.clone(),
+                        engine_path.clone(),
+                        python_exe.clone(),
+                    ));
+                }
+            }
+        }
+        let _ = std::fs::remove_file(&endpoint);
+    });
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn spawn_listener(
+    endpoint: String,
+    vault_path: String,
+    tokens: Arc<Mutex<HashMap<String, TokenGrant>>>,
+    mut shutdown: watch::Receiver<bool>,
+    password: String,
+    backend: Option<SecretBackendConfig>,
+    engine_path: PathBuf,
+    python_exe: String,
+) -> Result<(), String> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&endpoint)
+        .map_err(|e| format!("Failed to create vault broker pipe: {}", e))?;
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                changed = shutdown.changed() => {
+                    if changed.is_err() || *shutdown.borrow() {
+                        break;
+                    }
+                }
+                connected = server.connect() => {
+                    if connected.is_err() {
+                        continue;
+                    }
+                    // Windows named pipe instances are single-use: swap in a
+                    // fresh instance for the next client before handing the
+                    // connected one off to its handler task.
+                    let next = match ServerOptions::new().create(&endpoint) {
+                        Ok(next) => next,
+                        Err(_) => break,
+                    };
+                    let connected_server = std::mem::replace(&mut server, next);
+                    tauri::async_runtime::spawn(handle_connection(
+                        connected_server,
+                        vault_path.clone(),
+                        tokens.clone(),
+                        password.clone(),
+                        backend.clone(),
+                        engine_path.clone(),
+                        python_exe.clone(),
+                    ));
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
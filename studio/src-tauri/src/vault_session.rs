@@ -0,0 +1,122 @@
+//! In-memory unlock session for the project vault.
+//!
+//! Every `vault_*` command used to read `SKULDBOT_VAULT_PASSWORD` from the
+//! process environment on every call, meaning the master password sat in
+//! the environment indefinitely. This keeps the password in memory only
+//! for as long as the vault stays unlocked, zeroizing and evicting it after
+//! an idle timeout — the same unlocked-session-with-idle-re-lock shape SSH
+//! agents and credential managers use.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+use crate::vault_backend::SecretBackendConfig;
+
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 15 * 60;
+
+fn idle_timeout() -> Duration {
+    let secs = std::env::var("SKULDBOT_VAULT_IDLE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+struct UnlockedVault {
+    password: Zeroizing<Vec<u8>>,
+    backend: Option<SecretBackendConfig>,
+    last_access: Instant,
+}
+
+/// Tauri-managed state: one unlocked session per vault path.
+pub struct VaultSessionStore(Mutex<HashMap<String, UnlockedVault>>);
+
+impl VaultSessionStore {
+    pub fn new() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+
+    pub fn unlock(&self, path: &str, password: &str, backend: Option<SecretBackendConfig>) {
+        self.0.lock().unwrap().insert(
+            path.to_string(),
+            UnlockedVault {
+                password: Zeroizing::new(password.as_bytes().to_vec()),
+                backend,
+                last_access: Instant::now(),
+            },
+        );
+    }
+
+    pub fn lock(&self, path: &str) {
+        self.0.lock().unwrap().remove(path);
+    }
+
+    /// Returns the cached password + backend config for `path` if a session
+    /// is open and hasn't gone idle, refreshing its last-access time.
+    pub fn touch(&self, path: &str) -> Option<(String, Option<SecretBackendConfig>)> {
+        // An idle session reads as locked but is left for `sweep_expired`
+        // to evict, so the sweeper also stops its credential broker.
+        let mut sessions = self.0.lock().unwrap();
+        if Self::is_expired(&sessions, path) {
+            return None;
+        }
+        let session = sessions.get_mut(path)?;
+        session.last_access = Instant::now();
+        Some((
+            String::from_utf8_lossy(&session.password).to_string(),
+            session.backend.clone(),
+        ))
+    }
+
+    pub fn is_unlocked(&self, path: &str) -> bool {
+        let sessions = self.0.lock().unwrap();
+        sessions.contains_key(path) && !Self::is_expired(&sessions, path)
+    }
+
+    fn is_expired(sessions: &HashMap<String, UnlockedVault>, path: &str) -> bool {
+        sessions
+            .get(path)
+            .map(|s| s.last_access.elapsed() > idle_timeout())
+            .unwrap_or(false)
+    }
+
+    /// Evict every session that's gone idle past the configured timeout,
+    /// returning the paths that were evicted so callers can tear down
+    /// anything else keyed on an unlocked session (e.g. the credential
+    /// broker). Run periodically from a background task.
+    pub fn sweep_expired(&self) -> Vec<String> {
+        let timeout = idle_timeout();
+        let mut sessions = self.0.lock().unwrap();
+        let expired: Vec<String> = sessions
+            .iter()
+            .filter(|(_, s)| s.last_access.elapsed() > timeout)
+            .map(|(path, _)| path.clone())
+            .collect();
+        sessions.retain(|_, s| s.last_access.elapsed() <= timeout);
+        expired
+    }
+}
+
+/// Periodically evict idle vault sessions so an unlocked vault doesn't stay
+/// unlocked in memory forever. Also stops each evicted session's running
+/// credential broker — otherwise a bot spawned after the sweep could still
+/// mint grants through a broker process that outlived the session it was
+/// started for, even though `vault_is_unlocked` now reports locked.
+pub fn spawn_auto_lock_sweeper(app: tauri::AppHandle) {
+    use tauri::Manager;
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            if let Some(store) = app.try_state::<VaultSessionStore>() {
+                let expired = store.sweep_expired();
+                if let Some(broker) = app.try_state::<crate::vault_broker::CredentialBrokerStore>() {
+                    for path in expired {
+                        broker.stop(&path);
+                    }
+                }
+            }
+        }
+    });
+}